@@ -0,0 +1,161 @@
+use crate::{
+    clock::{Clock, SystemClock},
+    config::{CpmAlarmConfig, Timings},
+    notify::ring_bell,
+};
+use std::time::Instant;
+
+pub enum AlarmEvent {
+    Triggered(u64),
+    Cleared(u64),
+}
+
+pub struct CpmAlarm<C: Clock = SystemClock> {
+    clock: C,
+    config: CpmAlarmConfig,
+    window_start: Instant,
+    window_clicks: u64,
+    below_since: Option<Instant>,
+    triggered: bool,
+}
+
+impl<C: Clock> CpmAlarm<C> {
+    pub fn with_clock(config: CpmAlarmConfig, clock: C) -> Self {
+        Self {
+            window_start: clock.now(),
+            clock,
+            config,
+            window_clicks: 0,
+            below_since: None,
+            triggered: false,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.min_cpm > 0
+    }
+
+    // Samples the click counter and reports when the alarm fires or clears
+    pub fn check(&mut self, clicks: u64) -> Option<AlarmEvent> {
+        if !self.is_enabled() {
+            return None;
+        }
+
+        let now = self.clock.now();
+        let elapsed = now.duration_since(self.window_start);
+        if elapsed < Timings::CPM_SAMPLE_WINDOW {
+            return None;
+        }
+
+        // CPM over the last sample window rather than the whole session
+        let window_cpm = clicks.saturating_sub(self.window_clicks) * 60_000
+            / elapsed.as_millis().max(1) as u64;
+        self.window_start = now;
+        self.window_clicks = clicks;
+
+        if window_cpm >= self.config.min_cpm {
+            self.below_since = None;
+            if self.triggered {
+                self.triggered = false;
+                return Some(AlarmEvent::Cleared(window_cpm));
+            }
+            return None;
+        }
+
+        let below_since = *self.below_since.get_or_insert(now);
+        if !self.triggered && now.duration_since(below_since) >= self.config.sustain() {
+            self.triggered = true;
+            if self.config.bell {
+                ring_bell();
+            }
+            return Some(AlarmEvent::Triggered(window_cpm));
+        }
+
+        None
+    }
+
+    pub fn reset(&mut self, clicks: u64) {
+        self.window_start = self.clock.now();
+        self.window_clicks = clicks;
+        self.below_since = None;
+        self.triggered = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FakeClock;
+
+    // 100 CPM floor held for 30s; one sample window is 10s, so 100 CPM is ~17 clicks a window
+    fn alarm(clock: &FakeClock) -> CpmAlarm<FakeClock> {
+        let config = CpmAlarmConfig {
+            min_cpm: 100,
+            sustain_secs: 30,
+            bell: false,
+        };
+        CpmAlarm::with_clock(config, clock.clone())
+    }
+
+    // Advances one sample window during which `per_window` clicks landed
+    fn sample(
+        alarm: &mut CpmAlarm<FakeClock>,
+        clock: &FakeClock,
+        clicks: &mut u64,
+        per_window: u64,
+    ) -> Option<AlarmEvent> {
+        clock.advance(Timings::CPM_SAMPLE_WINDOW);
+        *clicks += per_window;
+        alarm.check(*clicks)
+    }
+
+    #[test]
+    fn fires_once_cpm_stays_low_for_the_sustain_period() {
+        let clock = FakeClock::new();
+        let mut alarm = alarm(&clock);
+        let mut clicks = 0;
+
+        // Low from the first sample; below_since starts there, so the fourth sample is 30s on
+        for _ in 0..3 {
+            assert!(sample(&mut alarm, &clock, &mut clicks, 5).is_none());
+        }
+        let event = sample(&mut alarm, &clock, &mut clicks, 5);
+        assert!(matches!(event, Some(AlarmEvent::Triggered(30))));
+
+        // Already triggered, so staying low doesn't fire again
+        assert!(sample(&mut alarm, &clock, &mut clicks, 5).is_none());
+    }
+
+    #[test]
+    fn brief_dips_stay_quiet() {
+        let clock = FakeClock::new();
+        let mut alarm = alarm(&clock);
+        let mut clicks = 0;
+
+        for _ in 0..5 {
+            assert!(sample(&mut alarm, &clock, &mut clicks, 5).is_none());
+            assert!(sample(&mut alarm, &clock, &mut clicks, 5).is_none());
+            // Recovering restarts the sustain period
+            assert!(sample(&mut alarm, &clock, &mut clicks, 50).is_none());
+        }
+        // Checks inside one sample window don't count as samples
+        clock.advance(Timings::CPM_SAMPLE_WINDOW / 2);
+        assert!(alarm.check(clicks).is_none());
+    }
+
+    #[test]
+    fn clears_when_cpm_recovers() {
+        let clock = FakeClock::new();
+        let mut alarm = alarm(&clock);
+        let mut clicks = 0;
+
+        for _ in 0..4 {
+            sample(&mut alarm, &clock, &mut clicks, 0);
+        }
+        assert!(alarm.triggered);
+
+        let event = sample(&mut alarm, &clock, &mut clicks, 50);
+        assert!(matches!(event, Some(AlarmEvent::Cleared(300))));
+        assert!(sample(&mut alarm, &clock, &mut clicks, 50).is_none());
+    }
+}
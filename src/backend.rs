@@ -0,0 +1,216 @@
+use crate::{
+    history::InputHistory,
+    logger::{LogLevel, Logger},
+    types::Position,
+};
+use anyhow::Result;
+use enigo::{Axis, Button, Coordinate, Direction, Enigo, Key, Keyboard, Mouse};
+use std::sync::Arc;
+
+// The lowest-level input operations the bot performs
+pub trait InputBackend: Send {
+    fn move_mouse(&mut self, pos: Position) -> Result<()>;
+    fn button(&mut self, button: Button, direction: Direction) -> Result<()>;
+    fn scroll(&mut self, length: i32) -> Result<()>;
+    fn key(&mut self, key: Key, direction: Direction) -> Result<()>;
+    fn main_display(&self) -> Result<(i32, i32)>;
+    fn location(&self) -> Result<Position>;
+}
+
+impl InputBackend for Enigo {
+    fn move_mouse(&mut self, pos: Position) -> Result<()> {
+        Ok(Mouse::move_mouse(self, pos.x, pos.y, Coordinate::Abs)?)
+    }
+
+    fn button(&mut self, button: Button, direction: Direction) -> Result<()> {
+        Ok(Mouse::button(self, button, direction)?)
+    }
+
+    fn scroll(&mut self, length: i32) -> Result<()> {
+        Ok(Mouse::scroll(self, length, Axis::Vertical)?)
+    }
+
+    fn key(&mut self, key: Key, direction: Direction) -> Result<()> {
+        Ok(Keyboard::key(self, key, direction)?)
+    }
+
+    fn main_display(&self) -> Result<(i32, i32)> {
+        Ok(Mouse::main_display(self)?)
+    }
+
+    fn location(&self) -> Result<Position> {
+        let (x, y) = Mouse::location(self)?;
+        Ok(Position::new(x, y))
+    }
+}
+
+impl<B: InputBackend + ?Sized> InputBackend for Box<B> {
+    fn move_mouse(&mut self, pos: Position) -> Result<()> {
+        (**self).move_mouse(pos)
+    }
+
+    fn button(&mut self, button: Button, direction: Direction) -> Result<()> {
+        (**self).button(button, direction)
+    }
+
+    fn scroll(&mut self, length: i32) -> Result<()> {
+        (**self).scroll(length)
+    }
+
+    fn key(&mut self, key: Key, direction: Direction) -> Result<()> {
+        (**self).key(key, direction)
+    }
+
+    fn main_display(&self) -> Result<(i32, i32)> {
+        (**self).main_display()
+    }
+
+    fn location(&self) -> Result<Position> {
+        (**self).location()
+    }
+}
+
+// Logs clicks and scrolls instead of performing them. Moves are tracked silently so
+// the logged coordinates match what would have been clicked.
+pub struct DryRunBackend<B> {
+    inner: B,
+    logger: Arc<Logger>,
+    cursor: Option<Position>,
+}
+
+impl<B: InputBackend> DryRunBackend<B> {
+    pub fn new(inner: B, logger: Arc<Logger>) -> Self {
+        Self {
+            inner,
+            logger,
+            cursor: None,
+        }
+    }
+
+    fn describe_cursor(&self) -> String {
+        match self.cursor.or_else(|| self.inner.location().ok()) {
+            Some(pos) => format!("({}, {})", pos.x, pos.y),
+            None => "(unknown)".to_string(),
+        }
+    }
+}
+
+impl<B: InputBackend> InputBackend for DryRunBackend<B> {
+    fn move_mouse(&mut self, pos: Position) -> Result<()> {
+        self.cursor = Some(pos);
+        Ok(())
+    }
+
+    fn button(&mut self, button: Button, direction: Direction) -> Result<()> {
+        self.logger.log(
+            LogLevel::Info,
+            &format!("[dry run] {:?} {:?} at {}", button, direction, self.describe_cursor()),
+        );
+        Ok(())
+    }
+
+    fn scroll(&mut self, length: i32) -> Result<()> {
+        self.logger.log(
+            LogLevel::Info,
+            &format!("[dry run] scroll {} at {}", length, self.describe_cursor()),
+        );
+        Ok(())
+    }
+
+    fn key(&mut self, key: Key, direction: Direction) -> Result<()> {
+        self.logger.log(LogLevel::Info, &format!("[dry run] key {:?} {:?}", key, direction));
+        Ok(())
+    }
+
+    fn main_display(&self) -> Result<(i32, i32)> {
+        self.inner.main_display()
+    }
+
+    fn location(&self) -> Result<Position> {
+        match self.cursor {
+            Some(pos) => Ok(pos),
+            None => self.inner.location(),
+        }
+    }
+}
+
+// Wraps another backend and records every action into the input history
+pub struct RecordingBackend<B> {
+    inner: B,
+    history: Arc<InputHistory>,
+}
+
+impl<B: InputBackend> RecordingBackend<B> {
+    pub fn new(inner: B, history: Arc<InputHistory>) -> Self {
+        Self { inner, history }
+    }
+}
+
+impl<B: InputBackend> InputBackend for RecordingBackend<B> {
+    fn move_mouse(&mut self, pos: Position) -> Result<()> {
+        self.history.record(format!("move ({}, {})", pos.x, pos.y));
+        self.inner.move_mouse(pos)
+    }
+
+    fn button(&mut self, button: Button, direction: Direction) -> Result<()> {
+        self.history.record(format!("{:?} {:?}", button, direction).to_lowercase());
+        self.inner.button(button, direction)
+    }
+
+    fn scroll(&mut self, length: i32) -> Result<()> {
+        self.history.record(format!("scroll {}", length));
+        self.inner.scroll(length)
+    }
+
+    fn key(&mut self, key: Key, direction: Direction) -> Result<()> {
+        self.history.record(format!("key {:?} {:?}", key, direction).to_lowercase());
+        self.inner.key(key, direction)
+    }
+
+    fn main_display(&self) -> Result<(i32, i32)> {
+        self.inner.main_display()
+    }
+
+    fn location(&self) -> Result<Position> {
+        self.inner.location()
+    }
+}
+
+// Stands in for the real mouse in tests: remembers the cursor and can be told to fail clicks
+#[cfg(test)]
+#[derive(Default)]
+pub struct NullBackend {
+    pub cursor: Position,
+    pub fail_clicks: bool,
+}
+
+#[cfg(test)]
+impl InputBackend for NullBackend {
+    fn move_mouse(&mut self, pos: Position) -> Result<()> {
+        self.cursor = pos;
+        Ok(())
+    }
+
+    fn button(&mut self, _button: Button, _direction: Direction) -> Result<()> {
+        if self.fail_clicks {
+            anyhow::bail!("click failed");
+        }
+        Ok(())
+    }
+
+    fn scroll(&mut self, _length: i32) -> Result<()> {
+        Ok(())
+    }
+
+    fn key(&mut self, _key: Key, _direction: Direction) -> Result<()> {
+        Ok(())
+    }
+
+    fn main_display(&self) -> Result<(i32, i32)> {
+        Ok((1920, 1440))
+    }
+
+    fn location(&self) -> Result<Position> {
+        Ok(self.cursor)
+    }
+}
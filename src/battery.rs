@@ -0,0 +1,71 @@
+use crate::{
+    clock::{Clock, SystemClock},
+    config::BatteryConfig,
+};
+use std::{fs, path::Path, time::Instant};
+
+const POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
+
+pub enum BatteryEvent {
+    // Running on battery below the configured floor; carries the charge percentage
+    Low(u8),
+}
+
+// Watches the laptop battery, reporting once when it runs low until it is charged back up or
+// plugged in. Reads the Linux power-supply files; elsewhere, or without a battery, it never fires.
+pub struct BatteryMonitor<C: Clock = SystemClock> {
+    clock: C,
+    config: BatteryConfig,
+    last_check: Option<Instant>,
+    tripped: bool,
+}
+
+impl<C: Clock> BatteryMonitor<C> {
+    pub fn with_clock(config: BatteryConfig, clock: C) -> Self {
+        Self {
+            clock,
+            config,
+            last_check: None,
+            tripped: false,
+        }
+    }
+
+    pub fn check(&mut self) -> Option<BatteryEvent> {
+        if !self.config.enabled {
+            return None;
+        }
+        if self.last_check.is_some_and(|at| self.clock.elapsed(at) < self.config.interval()) {
+            return None;
+        }
+        self.last_check = Some(self.clock.now());
+
+        let (percent, discharging) = read_battery()?;
+        if !discharging || percent >= self.config.min_percent {
+            self.tripped = false;
+            return None;
+        }
+        if self.tripped {
+            return None;
+        }
+        self.tripped = true;
+        Some(BatteryEvent::Low(percent))
+    }
+}
+
+// Charge percentage of the first battery and whether it is discharging
+fn read_battery() -> Option<(u8, bool)> {
+    fs::read_dir(POWER_SUPPLY_DIR)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|path| read_trimmed(path, "type").is_some_and(|kind| kind == "Battery"))
+        .and_then(|path| {
+            let percent = read_trimmed(&path, "capacity")?.parse().ok()?;
+            let discharging = read_trimmed(&path, "status")? == "Discharging";
+            Some((percent, discharging))
+        })
+}
+
+fn read_trimmed(dir: &Path, file: &str) -> Option<String> {
+    fs::read_to_string(dir.join(file)).ok().map(|contents| contents.trim().to_string())
+}
@@ -1,299 +1,2484 @@
 use crate::{
-    config::{GamePositions, SoulsPositions, Timings, UpgradePositions},
+    alarm::{AlarmEvent, CpmAlarm},
+    backend::{DryRunBackend, InputBackend, RecordingBackend},
+    battery::{BatteryEvent, BatteryMonitor},
+    clock::{Clock, SystemClock},
+    focus::{FocusEvent, WindowFocus},
+    config::{
+        Config, MiningMode, NotifyEvent, PositionsConfig, Profile, ProfileSet, TasksConfig,
+        Timings, STATE_PATH, STATS_PATH,
+    },
+    health::{HealthEvent, HealthMonitor},
+    history::InputHistory,
     logger::{LogLevel, Logger},
+    macros::{self, Macro, MacroAction, MacroStep},
+    notify::Notifier,
+    pixel::{self, Rgb},
+    schedule::{Schedule, ScheduleEvent},
     stats::Stats,
-    types::{Position, TaskType},
+    types::{Action, ClickStep, CoordMode, Position, TaskOutcome, TaskType},
 };
-use anyhow::Result;
-use enigo::{Axis, Button, Coordinate, Direction, Enigo, Mouse, Settings};
-use parking_lot::RwLock;
+use anyhow::{Context, Result};
+use chrono::Local;
+use enigo::{Button, Direction, Enigo, Key, Settings};
+use parking_lot::{Mutex, RwLock};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use serde::Serialize;
 use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::Path,
     sync::{
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicI32, AtomicU8, AtomicUsize, Ordering},
         Arc,
     },
     time::{Duration, Instant},
 };
 use tokio::time;
 
-pub struct Bot {
+// Everything the UI and external tools show about the bot, read in one go
+#[derive(Debug, Clone, Serialize)]
+pub struct BotSnapshot {
+    // Either mining or tasks are running
+    pub active: bool,
+    pub mining_active: bool,
+    pub tasks_active: bool,
+    pub input_available: bool,
+    pub clicks: u64,
+    pub cpm: u64,
+    pub runtime_secs: u64,
+    pub tasks: Vec<TaskSnapshot>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskSnapshot {
+    #[serde(skip)]
+    pub task: TaskType,
+    pub name: &'static str,
+    pub enabled: bool,
+    pub secs_until_next: u64,
+    pub period_secs: u64,
+}
+
+pub struct Bot<C: Clock = SystemClock> {
+    clock: C,
+    config: Config,
     state: Arc<BotState>,
-    stats: Arc<Stats>,
+    stats: Arc<Stats<C>>,
     logger: Arc<Logger>,
-    task_manager: Arc<TaskManager>,
+    notifier: Notifier,
+    task_manager: Arc<TaskManager<C>>,
+    history: Arc<InputHistory>,
+    screen_size: RwLock<Option<(i32, i32)>>,
+    rng: Arc<Mutex<StdRng>>,
+    profiles: ProfileSet,
+    profile_index: AtomicUsize,
+    profile: RwLock<Arc<Profile>>,
+    // Log input instead of sending it
+    dry_run: bool,
+    // Write stats and task toggles to disk; off in tests so they leave the real files alone
+    persist: bool,
 }
 
+// Bits of `BotState::active`; mining and tasks can be paused separately
+const MINING_ACTIVE: u8 = 1;
+const TASKS_ACTIVE: u8 = 2;
+const ALL_ACTIVE: u8 = MINING_ACTIVE | TASKS_ACTIVE;
+
 struct BotState {
-    active: AtomicBool,
+    active: AtomicU8,
+    // When the bot was last activated or paused; starts at launch
+    last_toggle: RwLock<Instant>,
+    // When mining last started, for the mining ramp
+    mining_started: RwLock<Instant>,
     upgrades_enabled: AtomicBool,
     souls_enabled: AtomicBool,
+    relics_enabled: AtomicBool,
+    forge_enabled: AtomicBool,
+    alchemy_enabled: AtomicBool,
+    miner_enabled: AtomicBool,
+    macro_enabled: AtomicBool,
     prestige_enabled: AtomicBool,
+    // Last cursor position the bot commanded, so the mouse guard can tell bot moves from human ones
+    last_move_x: AtomicI32,
+    last_move_y: AtomicI32,
+    running_task: AtomicBool,
+    shutdown: AtomicBool,
+    // One-shot permission for prestige when confirmation is required
+    prestige_armed: AtomicBool,
+    prestige_ready_logged: AtomicBool,
+    // First press of a prestige enable that still needs its confirming second press
+    prestige_enable_pending: Mutex<Option<Instant>>,
+    // When the last prestige finished, for the post-prestige cooldown
+    last_prestige: Mutex<Option<Instant>>,
+    // Set when the input backend couldn't be created, so the UI can explain why nothing happens
+    input_unavailable: AtomicBool,
+    // Tasks queued by the run-now hotkeys, picked up on the next loop tick
+    forced_tasks: Mutex<Vec<TaskType>>,
+    // Set when the bot is paused mid-task; the task skips its remaining rows and closes its panel
+    stop_requested: AtomicBool,
 }
 
 impl BotState {
-    fn new() -> Self {
+    fn new(now: Instant) -> Self {
         Self {
-            active: AtomicBool::new(false),
+            active: AtomicU8::new(0),
+            last_toggle: RwLock::new(now),
+            mining_started: RwLock::new(now),
             upgrades_enabled: AtomicBool::new(true),
             souls_enabled: AtomicBool::new(true),
+            relics_enabled: AtomicBool::new(false),
+            forge_enabled: AtomicBool::new(false),
+            alchemy_enabled: AtomicBool::new(false),
+            miner_enabled: AtomicBool::new(false),
+            macro_enabled: AtomicBool::new(false),
             prestige_enabled: AtomicBool::new(true),
+            last_move_x: AtomicI32::new(0),
+            last_move_y: AtomicI32::new(0),
+            running_task: AtomicBool::new(false),
+            shutdown: AtomicBool::new(false),
+            prestige_armed: AtomicBool::new(false),
+            prestige_ready_logged: AtomicBool::new(false),
+            prestige_enable_pending: Mutex::new(None),
+            last_prestige: Mutex::new(None),
+            input_unavailable: AtomicBool::new(false),
+            forced_tasks: Mutex::new(Vec::new()),
+            stop_requested: AtomicBool::new(false),
+        }
+    }
+
+    fn enabled_flag(&self, task_type: TaskType) -> &AtomicBool {
+        match task_type {
+            TaskType::Upgrades => &self.upgrades_enabled,
+            TaskType::Souls => &self.souls_enabled,
+            TaskType::Relics => &self.relics_enabled,
+            TaskType::Forge => &self.forge_enabled,
+            TaskType::Alchemy => &self.alchemy_enabled,
+            TaskType::Miner => &self.miner_enabled,
+            TaskType::Macro => &self.macro_enabled,
+            TaskType::Prestige => &self.prestige_enabled,
+        }
+    }
+
+    // Restores the task toggles saved by `save_toggles`; tasks missing from the file keep defaults
+    fn load_toggles(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(());
+        }
+
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let saved: BTreeMap<String, bool> = serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        for task_type in TaskType::ALL {
+            if let Some(&enabled) = saved.get(task_type.name()) {
+                self.enabled_flag(task_type).store(enabled, Ordering::Relaxed);
+            }
         }
+        Ok(())
+    }
+
+    fn save_toggles(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let saved: BTreeMap<&str, bool> = TaskType::ALL
+            .iter()
+            .map(|&task_type| {
+                (task_type.name(), self.enabled_flag(task_type).load(Ordering::Relaxed))
+            })
+            .collect();
+        let contents = serde_json::to_string_pretty(&saved)?;
+        fs::write(path, contents).with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+// Held while a panel task runs; released on drop so a cancelled task can't leave it stuck
+struct TaskLock<'a>(&'a AtomicBool);
+
+impl<'a> TaskLock<'a> {
+    fn acquire(flag: &'a AtomicBool) -> Option<Self> {
+        (!flag.swap(true, Ordering::Acquire)).then_some(Self(flag))
     }
 }
 
-pub struct TaskManager {
-    last_upgrade: RwLock<Instant>,
-    last_souls: RwLock<Instant>,
-    last_prestige: RwLock<Instant>,
+impl Drop for TaskLock<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
 }
 
-impl TaskManager {
-    fn new() -> Self {
-        let now = Instant::now();
-        Self {
-            last_upgrade: RwLock::new(now),
-            last_souls: RwLock::new(now),
-            last_prestige: RwLock::new(now),
+// Keeps a mouse button down; released on drop so an interrupted hold can't leave it stuck
+struct ButtonHold<'a> {
+    input: &'a mut dyn InputBackend,
+    button: Button,
+}
+
+impl<'a> ButtonHold<'a> {
+    fn press(input: &'a mut dyn InputBackend, button: Button) -> Option<Self> {
+        input.button(button, Direction::Press).ok()?;
+        Some(Self { input, button })
+    }
+}
+
+impl Drop for ButtonHold<'_> {
+    fn drop(&mut self) {
+        let _ = self.input.button(self.button, Direction::Release);
+    }
+}
+
+// The current cycle of a task: when it is next due and how long the cycle was
+#[derive(Clone, Copy)]
+struct TaskTimer {
+    deadline: Instant,
+    period: Duration,
+}
+
+pub struct TaskManager<C: Clock = SystemClock> {
+    clock: C,
+    timers: RwLock<HashMap<TaskType, TaskTimer>>,
+    consecutive_errors: RwLock<HashMap<TaskType, u32>>,
+    intervals: RwLock<HashMap<TaskType, Duration>>,
+    // Widened intervals for tasks whose recent cycles found nothing to do
+    backoff: RwLock<HashMap<TaskType, Duration>>,
+    jitter_pct: f64,
+    rng: Arc<Mutex<StdRng>>,
+    // Set while the bot is paused; task clocks stand still until `resume`
+    paused_at: RwLock<Option<Instant>>,
+}
+
+impl<C: Clock> TaskManager<C> {
+    fn new(tasks: &TasksConfig, jitter_pct: f64, rng: Arc<Mutex<StdRng>>, clock: C) -> Self {
+        let manager = Self {
+            timers: RwLock::new(HashMap::new()),
+            consecutive_errors: RwLock::new(HashMap::new()),
+            intervals: RwLock::new(HashMap::new()),
+            backoff: RwLock::new(HashMap::new()),
+            jitter_pct: jitter_pct.clamp(0.0, 99.0),
+            rng,
+            paused_at: RwLock::new(Some(clock.now())),
+            clock,
+        };
+        manager.reconfigure(tasks);
+        manager
+    }
+
+    // Applies a new set of intervals and starts every task on a fresh full cycle
+    fn reconfigure(&self, tasks: &TasksConfig) {
+        for task_type in TaskType::ALL {
+            self.set_interval(task_type, tasks.interval(task_type));
+            self.consecutive_errors.write().remove(&task_type);
+            self.backoff.write().remove(&task_type);
+            self.update_last_run(task_type);
+        }
+    }
+
+    // Takes effect on the next tick of the bot loop
+    pub fn set_interval(&self, task_type: TaskType, interval: Duration) {
+        let previous = self.intervals.write().insert(task_type, interval);
+
+        // Shift the pending deadline so the current cycle uses the new interval too
+        if let (Some(previous), Some(timer)) = (previous, self.timers.write().get_mut(&task_type)) {
+            if interval >= previous {
+                timer.deadline += interval - previous;
+                timer.period += interval - previous;
+            } else {
+                let shorter = previous - interval;
+                timer.deadline = timer.deadline.checked_sub(shorter).unwrap_or(timer.deadline);
+                timer.period = timer.period.saturating_sub(shorter);
+            }
         }
     }
 
+    pub fn get_interval(&self, task_type: TaskType) -> Duration {
+        self.intervals.read().get(&task_type).copied().unwrap_or_default()
+    }
+
+    // Length of the task's current (possibly jittered) cycle
+    pub fn get_period(&self, task_type: TaskType) -> Duration {
+        self.timers
+            .read()
+            .get(&task_type)
+            .map_or_else(|| self.get_interval(task_type), |timer| timer.period)
+    }
+
     fn should_run_task(&self, task_type: TaskType) -> bool {
-        let now = Instant::now();
-        let elapsed = match task_type {
-            TaskType::Upgrades => now.duration_since(*self.last_upgrade.read()),
-            TaskType::Souls => now.duration_since(*self.last_souls.read()),
-            TaskType::Prestige => now.duration_since(*self.last_prestige.read()),
-        };
+        if self.is_paused() {
+            return false;
+        }
+        self.timers
+            .read()
+            .get(&task_type)
+            .is_some_and(|timer| self.clock.now() >= timer.deadline)
+    }
+
+    fn update_last_run(&self, task_type: TaskType) {
+        let period = self.jittered_interval(task_type);
+        self.set_timer(task_type, self.now() + period, period);
+    }
+
+    // Makes a task due right away, used for `run_on_start` tasks on activation
+    fn make_due(&self, task_type: TaskType) {
+        let period = self.get_interval(task_type);
+        self.set_timer(task_type, self.now(), period);
+    }
 
-        let interval = match task_type {
-            TaskType::Upgrades => Timings::UPGRADE_INTERVAL,
-            TaskType::Souls => Timings::SOULS_INTERVAL,
-            TaskType::Prestige => Timings::PRESTIGE_INTERVAL,
+    // Freezes every task's countdown
+    fn pause(&self) {
+        self.paused_at.write().get_or_insert_with(|| self.clock.now());
+    }
+
+    // Pushes every deadline back by the time spent paused so countdowns pick up where they stopped
+    fn resume(&self) {
+        let Some(paused_at) = self.paused_at.write().take() else {
+            return;
         };
+        let paused_for = self.clock.elapsed(paused_at);
+        for timer in self.timers.write().values_mut() {
+            timer.deadline += paused_for;
+        }
+    }
 
-        elapsed > interval
+    fn is_paused(&self) -> bool {
+        self.paused_at.read().is_some()
     }
 
-    fn update_last_run(&self, task_type: TaskType) {
-        let now = Instant::now();
-        match task_type {
-            TaskType::Upgrades => *self.last_upgrade.write() = now,
-            TaskType::Souls => *self.last_souls.write() = now,
-            TaskType::Prestige => *self.last_prestige.write() = now,
+    // The instant countdowns are measured against; stands still while paused
+    fn now(&self) -> Instant {
+        self.paused_at.read().unwrap_or_else(|| self.clock.now())
+    }
+
+    // Returns the task's consecutive error count after recording this run
+    fn record_result(&self, task_type: TaskType, failed: bool) -> u32 {
+        let mut errors = self.consecutive_errors.write();
+        let count = errors.entry(task_type).or_insert(0);
+        *count = if failed { *count + 1 } else { 0 };
+        *count
+    }
+
+    fn set_timer(&self, task_type: TaskType, deadline: Instant, period: Duration) {
+        self.timers.write().insert(task_type, TaskTimer { deadline, period });
+    }
+
+    // Doubles the task's interval for the following cycles, up to `cap`, returning the new one
+    fn back_off(&self, task_type: TaskType, cap: Duration) -> Duration {
+        let mut backoff = self.backoff.write();
+        let current = backoff
+            .get(&task_type)
+            .copied()
+            .unwrap_or_else(|| self.get_interval(task_type));
+        let widened = (current * 2).min(cap).max(current);
+        backoff.insert(task_type, widened);
+        widened
+    }
+
+    // Returns the task to its configured interval, reporting whether it had been backed off
+    fn reset_backoff(&self, task_type: TaskType) -> bool {
+        self.backoff.write().remove(&task_type).is_some()
+    }
+
+    fn jittered_interval(&self, task_type: TaskType) -> Duration {
+        let interval = self
+            .backoff
+            .read()
+            .get(&task_type)
+            .copied()
+            .unwrap_or_else(|| self.get_interval(task_type));
+        if self.jitter_pct <= 0.0 {
+            return interval;
         }
+
+        let offset = self.rng.lock().gen_range(-self.jitter_pct..=self.jitter_pct);
+        interval.mul_f64(1.0 + offset / 100.0)
     }
 
     pub fn get_time_until_next(&self, task_type: TaskType) -> Duration {
-        let elapsed = match task_type {
-            TaskType::Upgrades => self.last_upgrade.read().elapsed(),
-            TaskType::Souls => self.last_souls.read().elapsed(),
-            TaskType::Prestige => self.last_prestige.read().elapsed(),
-        };
+        self.timers
+            .read()
+            .get(&task_type)
+            .map_or(Duration::ZERO, |timer| {
+                timer.deadline.saturating_duration_since(self.now())
+            })
+    }
 
-        let interval = match task_type {
-            TaskType::Upgrades => Timings::UPGRADE_INTERVAL,
-            TaskType::Souls => Timings::SOULS_INTERVAL,
-            TaskType::Prestige => Timings::PRESTIGE_INTERVAL,
-        };
+    // The soonest-due task among those `enabled` accepts, ties going to the earlier one in
+    // `TaskType::ALL`
+    pub fn next_due(&self, enabled: impl Fn(TaskType) -> bool) -> Option<(TaskType, Duration)> {
+        TaskType::ALL
+            .into_iter()
+            .filter(|&task_type| enabled(task_type))
+            .map(|task_type| (task_type, self.get_time_until_next(task_type)))
+            .min_by_key(|&(_, remaining)| remaining)
+    }
 
-        interval.saturating_sub(elapsed)
+    // True when prestige will come due within `window`, so spending currency now would be wasted
+    pub fn prestige_imminent(&self, window: Duration) -> bool {
+        !window.is_zero()
+            && self.timers.read().contains_key(&TaskType::Prestige)
+            && self.get_time_until_next(TaskType::Prestige) <= window
     }
 }
 
 impl Bot {
-    pub fn new() -> Self {
+    pub fn new(config: Config, dry_run: bool) -> Self {
+        Self::with_clock(config, dry_run, SystemClock)
+    }
+}
+
+impl<C: Clock> Bot<C> {
+    pub fn with_clock(config: Config, dry_run: bool, clock: C) -> Self {
+        let history = Arc::new(InputHistory::new(config.input_history.capacity));
+        let rng = Arc::new(Mutex::new(match config.randomization.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        }));
+        let profiles = ProfileSet::from_config(&config);
+        let profile = profiles.get(0);
+        let task_manager = Arc::new(TaskManager::new(
+            &profile.tasks,
+            config.randomization.interval_jitter_pct,
+            rng.clone(),
+            clock.clone(),
+        ));
+        let stats =
+            Arc::new(Stats::with_clock(clock.clone()).with_warmup(config.display.cpm_warmup()));
+        let logger = Arc::new(Logger::new(config.logging.log_retention, &config.log_file));
+        if let Err(e) = stats.load_from_disk(STATS_PATH) {
+            logger.log(LogLevel::Warning, &format!("Could not load lifetime stats: {:#}", e));
+        }
+        let state = Arc::new(BotState::new(clock.now()));
+        if let Err(e) = state.load_toggles(STATE_PATH) {
+            logger.log(LogLevel::Warning, &format!("Could not load task toggles: {:#}", e));
+        }
+        if config.pixel_check.enabled && !pixel::is_supported() {
+            logger.log(
+                LogLevel::Warning,
+                "pixel_check is enabled but this build lacks the pixel-check feature",
+            );
+        }
+
         Self {
-            state: Arc::new(BotState::new()),
-            stats: Arc::new(Stats::new()),
-            logger: Arc::new(Logger::new()),
-            task_manager: Arc::new(TaskManager::new()),
+            clock,
+            notifier: Notifier::new(config.notify.clone()),
+            config,
+            state,
+            stats,
+            logger,
+            task_manager,
+            history,
+            screen_size: RwLock::new(None),
+            rng,
+            profiles,
+            profile_index: AtomicUsize::new(0),
+            profile: RwLock::new(profile),
+            dry_run,
+            persist: true,
+        }
+    }
+
+    // Sets up the input backend the bot clicks through and learns the screen size from it
+    fn open_input(&self) -> Result<RecordingBackend<Box<dyn InputBackend>>> {
+        let enigo = match Enigo::new(&Settings::default()) {
+            Ok(enigo) => enigo,
+            Err(e) => {
+                self.state.input_unavailable.store(true, Ordering::Relaxed);
+                let message = format!("Input unavailable, the bot can't click: {}", e);
+                self.logger.log(LogLevel::Error, &message);
+                self.notifier.notify(NotifyEvent::InputUnavailable, &message);
+                return Err(e).context("Failed to set up mouse and keyboard input");
+            }
+        };
+        let backend: Box<dyn InputBackend> = if self.dry_run {
+            self.logger.log(LogLevel::Warning, "Dry run: clicks and scrolls are logged, not sent");
+            Box::new(DryRunBackend::new(enigo, self.logger.clone()))
+        } else {
+            Box::new(enigo)
+        };
+        let input = RecordingBackend::new(backend, self.history.clone());
+        match input.main_display() {
+            Ok(size) => {
+                *self.screen_size.write() = Some(size);
+                self.report_off_screen_positions(size);
+                let (sx, sy) = self.config.resolution.scale_factors(size);
+                if sx != 1.0 || sy != 1.0 {
+                    self.logger.log(
+                        LogLevel::Info,
+                        &format!("Screen is {}x{}, scaling positions by {:.2}x{:.2}", size.0, size.1, sx, sy),
+                    );
+                }
+            }
+            Err(e) => self.logger.log(
+                LogLevel::Warning,
+                &format!("Could not detect screen resolution, percent positions disabled: {}", e),
+            ),
+        }
+        Ok(input)
+    }
+
+    // Walks the cursor over every configured target of the current profile, pausing on each so
+    // it can be checked by eye. Only clicks when asked to.
+    pub async fn verify_positions(
+        &self,
+        click: bool,
+        report: impl Fn(&str, Option<Position>),
+    ) -> Result<()> {
+        let mut input = self.open_input()?;
+
+        for (name, target) in self.profile().positions.named() {
+            let Some(pos) = self.resolve(target) else {
+                report(&name, None);
+                continue;
+            };
+            report(&name, Some(pos));
+            self.travel_to(&mut input, pos).await?;
+            if click && self.in_safe_area(pos) {
+                tokio::time::sleep(Timings::CLICK_DELAY).await;
+                input.button(Button::Left, Direction::Click)?;
+            }
+            tokio::time::sleep(Timings::VERIFY_DWELL).await;
         }
+        Ok(())
+    }
+
+    // Records clicks until Escape, stored in reference-resolution pixels so the macro plays back
+    // like any other configured position
+    pub async fn record_macro(&self, on_step: impl Fn(&MacroStep)) -> Result<Macro> {
+        self.open_input()?;
+        let screen = self.get_screen_size();
+        let resolution = &self.config.resolution;
+        let to_target = |pos| match screen {
+            Some(screen) => resolution.unscale_position(pos, screen).into(),
+            None => pos.into(),
+        };
+        Ok(macros::record(to_target, on_step).await)
     }
 
     pub async fn run_loop(&self) -> Result<()> {
-        let mut enigo = Enigo::new(&Settings::default())?;
-        let mut mining_interval = time::interval(Timings::MINING_DELAY);
-        
+        let mut input = self.open_input()?;
+        let mut mining_interval = time::interval(self.config.mining.delay());
+        let mut cpm_alarm = CpmAlarm::with_clock(self.config.cpm_alarm.clone(), self.clock.clone());
+        let mut health =
+            HealthMonitor::with_clock(self.config.health_check.clone(), self.clock.clone());
+        let mut schedule = Schedule::new(&self.config.schedule);
+        let mut focus = WindowFocus::with_clock(self.config.focus_guard.clone(), self.clock.clone());
+        let mut battery =
+            BatteryMonitor::with_clock(self.config.battery.clone(), self.clock.clone());
+        if self.config.health_check.enabled && !health.is_enabled() {
+            self.logger.log(
+                LogLevel::Warning,
+                "health_check is enabled but this build lacks the pixel-check feature",
+            );
+        }
+
         self.logger.log(LogLevel::Info, "Bot loop started");
+        if self.config.start_active {
+            if schedule.is_enabled() {
+                self.logger.log(LogLevel::Info, "start_active ignored, the schedule decides");
+            } else if !self.is_active() {
+                self.logger.log(LogLevel::Info, "Starting active (start_active is set)");
+                self.toggle();
+            }
+        }
 
-        loop {
+        while !self.is_shutting_down() {
             mining_interval.tick().await;
-            
+            self.check_schedule(&mut schedule);
+            self.run_forced_tasks(&mut input, &mut focus).await;
+
             if !self.is_active() {
-                tokio::time::sleep(Duration::from_millis(100)).await;
+                cpm_alarm.reset(self.stats.get_clicks());
+                health.reset();
+                tokio::time::sleep(Timings::IDLE_POLL).await;
+                continue;
+            }
+
+            // Clicks would land in whatever window is in front instead of the game
+            if !self.check_focus(&mut focus) {
+                cpm_alarm.reset(self.stats.get_clicks());
+                health.reset();
+                tokio::time::sleep(Timings::IDLE_POLL).await;
                 continue;
             }
 
-            self.perform_mining_click(&mut enigo);
-            self.check_and_run_tasks(&mut enigo).await;
+            // Ease into full speed after each activation instead of spiking straight to it
+            let delay = self.mining_delay();
+            if delay != mining_interval.period() {
+                mining_interval = time::interval_at(time::Instant::now() + delay, delay);
+            }
+
+            let mining = self.is_mining_active();
+            if mining {
+                match self.config.mining.mode {
+                    MiningMode::Click => self.perform_mining_click(&mut input),
+                    MiningMode::Hold => self.perform_mining_hold(&mut input).await,
+                }
+            }
+            if self.is_tasks_active() {
+                self.check_and_run_tasks(&mut input, &mut focus).await;
+            }
+            // Both watch the mining clicks, so they rest while only tasks run
+            if mining {
+                self.check_cpm_alarm(&mut cpm_alarm);
+                self.check_health(&mut health);
+            } else {
+                cpm_alarm.reset(self.stats.get_clicks());
+                health.reset();
+            }
+            self.check_limits();
+            self.check_battery(&mut battery);
         }
-    }
 
-    fn perform_mining_click(&self, enigo: &mut Enigo) {
-        let _ = enigo.move_mouse(GamePositions::MINING.x, GamePositions::MINING.y, Coordinate::Abs);
-        let _ = enigo.button(Button::Left, Direction::Click);
-        self.stats.increment_clicks();
+        // Never leave the game with a button held down
+        let _ = input.button(self.config.mining.button, Direction::Release);
+        Ok(())
     }
 
-    async fn check_and_run_tasks(&self, enigo: &mut Enigo) {
-        if self.state.upgrades_enabled.load(Ordering::Relaxed) 
-            && self.task_manager.should_run_task(TaskType::Upgrades) {
-            self.perform_upgrades(enigo).await;
-            self.task_manager.update_last_run(TaskType::Upgrades);
+    // Polls the focus guard and logs changes; false while another window is in front
+    fn check_focus(&self, focus: &mut WindowFocus<C>) -> bool {
+        match focus.check() {
+            Some(FocusEvent::Lost(title)) => self.logger.log(
+                LogLevel::Warning,
+                &format!("Game window not focused (\"{}\" is), waiting for focus", title),
+            ),
+            Some(FocusEvent::Regained) => {
+                self.logger.log(LogLevel::Info, "Game window focused again")
+            }
+            Some(FocusEvent::Unavailable) => self.logger.log(
+                LogLevel::Warning,
+                "focus_guard can't read the active window here (needs xdotool), clicking anyway",
+            ),
+            None => {}
         }
-        
-        if self.state.souls_enabled.load(Ordering::Relaxed) 
-            && self.task_manager.should_run_task(TaskType::Souls) {
-            self.perform_souls_upgrade(enigo).await;
-            self.task_manager.update_last_run(TaskType::Souls);
+        focus.is_focused()
+    }
+
+    fn check_cpm_alarm(&self, cpm_alarm: &mut CpmAlarm<C>) {
+        match cpm_alarm.check(self.stats.get_clicks()) {
+            Some(AlarmEvent::Triggered(cpm)) => {
+                let message = format!("CPM dropped to {} - bot may be stuck", cpm);
+                self.logger.log(LogLevel::Error, &message);
+                self.notifier.notify(NotifyEvent::CpmAlarm, &message);
+            }
+            Some(AlarmEvent::Cleared(cpm)) => {
+                self.logger.log(LogLevel::Success, &format!("CPM recovered to {}", cpm))
+            }
+            None => {}
         }
-        
-        if self.state.prestige_enabled.load(Ordering::Relaxed) 
-            && self.task_manager.should_run_task(TaskType::Prestige) {
-            self.perform_prestige(enigo).await;
-            self.task_manager.update_last_run(TaskType::Prestige);
+    }
+
+    fn check_limits(&self) {
+        let limits = &self.config.limits;
+        let reason = if limits.max_runtime().is_some_and(|max| self.stats.get_runtime() >= max) {
+            format!("the {} minute limit", limits.max_runtime_mins)
+        } else if limits.max_clicks().is_some_and(|max| self.stats.get_clicks() >= max) {
+            format!("the {} click limit", limits.max_clicks)
+        } else {
+            return;
+        };
+        let message = format!("Session reached {}, pausing", reason);
+        self.logger.log(LogLevel::Warning, &message);
+        self.notifier.notify(NotifyEvent::LimitReached, &message);
+        self.toggle();
+    }
+
+    fn check_battery(&self, battery: &mut BatteryMonitor<C>) {
+        let Some(BatteryEvent::Low(percent)) = battery.check() else {
+            return;
+        };
+        let message = format!("Battery at {}% and discharging, pausing", percent);
+        self.logger.log(LogLevel::Warning, &message);
+        self.notifier.notify(NotifyEvent::BatteryLow, &message);
+        // Already paused by hand, so toggling would resume it
+        if self.is_active() {
+            self.toggle();
         }
     }
 
-    async fn perform_upgrades(&self, enigo: &mut Enigo) {
-        self.logger.log(LogLevel::Task, "Running upgrades...");
-        
-        // Open upgrades panel
-        self.click_at(enigo, GamePositions::UPGRADE_ICON).await;
-        self.click_at(enigo, GamePositions::UPGRADES_TAB).await;
-        
-        // Click first 5 rows before scrolling
-        for (i, pos) in UpgradePositions::BEFORE_SCROLL.iter().enumerate() {
-            self.click_at(enigo, *pos).await;
-            if i == 2 {
-                // Small pause mid-way to ensure clicks register
-                tokio::time::sleep(Duration::from_millis(100)).await;
-            }
+    fn check_schedule(&self, schedule: &mut Schedule) {
+        let (activate, message) = match schedule.check() {
+            Some(ScheduleEvent::Start) => (true, "Schedule window opened"),
+            Some(ScheduleEvent::Stop) => (false, "Schedule window closed"),
+            None => return,
+        };
+        self.logger.log(LogLevel::Info, message);
+        if self.is_active() != activate {
+            self.toggle();
         }
-        
-        // Scroll down by 8 units to reveal more upgrades
-        self.scroll_at(enigo, GamePositions::SAFE_SCROLL_AREA, -8).await;
-        
-        // Click all rows after scrolling (positions have changed due to scroll)
-        for pos in &UpgradePositions::AFTER_SCROLL {
-            self.click_at(enigo, *pos).await;
+    }
+
+    fn check_health(&self, health: &mut HealthMonitor<C>) {
+        let Some(origin) = self.resolve(self.config.health_check.region) else {
+            return;
+        };
+        if let Some(HealthEvent::Frozen(checks)) = health.check(origin) {
+            let message = format!("Game looks frozen ({} identical checks), pausing", checks);
+            self.logger.log(LogLevel::Error, &message);
+            self.notifier.notify(NotifyEvent::GameFrozen, &message);
+            self.toggle();
+            health.reset();
         }
-        
-        // Reset scroll to original position
-        self.scroll_at(enigo, GamePositions::SAFE_SCROLL_AREA, 8).await;
-        
-        self.logger.log(LogLevel::Success, "Upgrades complete");
     }
 
-    async fn perform_souls_upgrade(&self, enigo: &mut Enigo) {
-        self.logger.log(LogLevel::Task, "Running souls upgrade...");
-        
-        // Open souls panel
-        self.click_at(enigo, GamePositions::UPGRADE_ICON).await;
-        self.click_at(enigo, GamePositions::SOULS_TAB).await;
-        
-        // Click first 6 rows
-        for pos in &SoulsPositions::BEFORE_SCROLL {
-            self.click_at(enigo, *pos).await;
+    fn perform_mining_click(&self, input: &mut dyn InputBackend) {
+        if self.state.running_task.load(Ordering::Acquire) || self.is_shutting_down() {
+            return;
         }
-        
-        // Scroll down and click last row
-        self.scroll_at(enigo, GamePositions::SAFE_SCROLL_AREA, -2).await;
-        self.click_at(enigo, SoulsPositions::AFTER_SCROLL).await;
-        
-        // Reset scroll
-        self.scroll_at(enigo, GamePositions::SAFE_SCROLL_AREA, 2).await;
-        
-        self.logger.log(LogLevel::Success, "Souls upgrade complete");
+        let Some(pos) = self.resolve(self.pick_point(self.profile().positions.mining)) else {
+            return;
+        };
+        let pos = self.apply_jitter(pos);
+        if !self.in_safe_area(pos) {
+            return;
+        }
+        let _ = self.move_to(input, pos);
+        let _ = input.button(self.config.mining.button, Direction::Click);
+        self.stats.increment_clicks();
+        self.trace_mining_click(pos);
     }
 
-    async fn perform_prestige(&self, enigo: &mut Enigo) {
-        self.logger.log(LogLevel::Task, "Running prestige...");
-        
-        self.click_at(enigo, GamePositions::PRESTIGE_BUTTON).await;
-        tokio::time::sleep(Timings::PRESTIGE_WAIT).await;
-        
-        self.click_at(enigo, GamePositions::PRESTIGE_CLAIM).await;
-        tokio::time::sleep(Timings::PRESTIGE_WAIT).await;
-        
-        self.click_at(enigo, GamePositions::PRESTIGE_CONFIRM).await;
-        tokio::time::sleep(Timings::PRESTIGE_COMPLETE_WAIT).await;
-        
-        self.logger.log(LogLevel::Success, "Prestige complete");
+    async fn perform_mining_hold(&self, input: &mut dyn InputBackend) {
+        if self.state.running_task.load(Ordering::Acquire) || self.is_shutting_down() {
+            return;
+        }
+        let Some(pos) = self.resolve(self.pick_point(self.profile().positions.mining)) else {
+            return;
+        };
+        let pos = self.apply_jitter(pos);
+        if !self.in_safe_area(pos) {
+            return;
+        }
+        if self.hold_at(input, pos, self.config.mining.hold()).await {
+            self.stats.increment_clicks();
+            self.trace_mining_click(pos);
+        }
     }
 
-    async fn click_at(&self, enigo: &mut Enigo, pos: Position) {
-        let _ = enigo.move_mouse(pos.x, pos.y, Coordinate::Abs);
-        tokio::time::sleep(Timings::CLICK_DELAY).await;
-        let _ = enigo.button(Button::Left, Direction::Click);
-        tokio::time::sleep(Timings::CLICK_DELAY).await;
+    fn trace_mining_click(&self, pos: Position) {
+        if self.config.logging.trace_clicks {
+            self.logger.log(LogLevel::Trace, &format!("Mining click at ({}, {})", pos.x, pos.y));
+        }
     }
 
-    async fn scroll_at(&self, enigo: &mut Enigo, pos: Position, amount: i32) {
-        let _ = enigo.move_mouse(pos.x, pos.y, Coordinate::Abs);
-        tokio::time::sleep(Timings::SCROLL_DELAY).await;
-        
-        for _ in 0..amount.abs() {
-            let _ = enigo.scroll(if amount > 0 { -1 } else { 1 }, Axis::Vertical);
-            tokio::time::sleep(Timings::POST_SCROLL_DELAY).await;
+    // Moves to `pos` and holds the mining button for `duration`, letting go early if the bot is
+    // paused or shutting down. The button is released even if this future is dropped mid-hold.
+    async fn hold_at(&self, input: &mut dyn InputBackend, pos: Position, duration: Duration) -> bool {
+        if self.move_to(input, pos).is_err() {
+            return false;
+        }
+        let Some(_hold) = ButtonHold::press(input, self.config.mining.button) else {
+            return false;
+        };
+
+        let started = self.clock.now();
+        while self.clock.elapsed(started) < duration {
+            if !self.is_mining_active() || self.is_shutting_down() {
+                return false;
+            }
+            let remaining = duration.saturating_sub(self.clock.elapsed(started));
+            tokio::time::sleep(remaining.min(Timings::IDLE_POLL)).await;
         }
+        true
     }
 
-    // Public interface methods
-    pub fn toggle(&self) {
-        let was_active = self.state.active.fetch_xor(true, Ordering::Relaxed);
-        let (status, level) = if !was_active {
-            self.stats.reset();
-            ("ACTIVATED", LogLevel::Success)
+    async fn check_and_run_tasks(&self, input: &mut dyn InputBackend, focus: &mut WindowFocus<C>) {
+        let tasks = self.profile().tasks.clone();
+        let mut due: Vec<TaskType> = TaskType::ALL
+            .into_iter()
+            .filter(|&task_type| {
+                self.is_task_enabled(task_type) && self.task_manager.should_run_task(task_type)
+            })
+            .collect();
+        if self.config.randomization.randomize_task_order {
+            order_due_tasks(&mut due, &tasks, Some(&mut *self.rng.lock()));
         } else {
-            ("PAUSED", LogLevel::Warning)
-        };
-        self.logger.log(level, &format!("Bot {}", status));
+            order_due_tasks(&mut due, &tasks, None);
+        }
+
+        // Spending tasks wait until a nearby prestige has reset the currency they'd use
+        if self.prestige_pending(&tasks) {
+            due.retain(|task_type| !task_type.spends_currency());
+        }
+
+        for task_type in due {
+            if self.is_shutting_down() {
+                return;
+            }
+            if task_type == TaskType::Prestige && !self.prestige_permitted() {
+                continue;
+            }
+            // Left due, so they run once the cooldown is over
+            if task_type.spends_currency() && self.in_prestige_cooldown(&tasks) {
+                continue;
+            }
+            if !self.check_focus(focus) {
+                return;
+            }
+            if !self.run_and_reschedule(input, task_type).await {
+                return;
+            }
+        }
     }
 
-    pub fn toggle_upgrades(&self) {
-        self.toggle_task(TaskType::Upgrades, &self.state.upgrades_enabled);
+    // Runs tasks queued from the run-now hotkeys, whether or not they're enabled, due or the bot
+    // is active
+    async fn run_forced_tasks(&self, input: &mut dyn InputBackend, focus: &mut WindowFocus<C>) {
+        // Left queued until the game is back in front
+        if self.state.forced_tasks.lock().is_empty() || !self.check_focus(focus) {
+            return;
+        }
+        let forced = std::mem::take(&mut *self.state.forced_tasks.lock());
+        for task_type in forced {
+            // Run-now skips the due check, not the arming a prestige needs
+            if task_type == TaskType::Prestige && !self.prestige_armed() {
+                self.logger.log(
+                    LogLevel::Warning,
+                    &format!(
+                        "Prestige not run - press {} to arm it first",
+                        self.profile().keybindings.arm_prestige
+                    ),
+                );
+                continue;
+            }
+            if self.is_shutting_down() || !self.run_and_reschedule(input, task_type).await {
+                return;
+            }
+        }
     }
 
-    pub fn toggle_souls(&self) {
-        self.toggle_task(TaskType::Souls, &self.state.souls_enabled);
+    // Runs the task and restarts its countdown, returning false if another task holds the lock
+    async fn run_and_reschedule(&self, input: &mut dyn InputBackend, task_type: TaskType) -> bool {
+        // Only one panel task may run at a time
+        let Some(_lock) = TaskLock::acquire(&self.state.running_task) else {
+            return false;
+        };
+        self.run_task(input, task_type).await;
+        // A stopped task stays due so it runs in full once the bot is resumed
+        let stopped = self.state.stop_requested.swap(false, Ordering::Relaxed);
+        if !stopped {
+            self.task_manager.update_last_run(task_type);
+        }
+        if task_type == TaskType::Prestige {
+            self.state.prestige_armed.store(false, Ordering::Relaxed);
+            let cooldown = self.profile().tasks.post_prestige_cooldown_secs;
+            if !stopped {
+                *self.state.last_prestige.lock() = Some(self.clock.now());
+                if cooldown > 0 {
+                    self.logger.log(
+                        LogLevel::Info,
+                        &format!("Holding upgrades, souls and relics for {}s", cooldown),
+                    );
+                }
+            }
+        }
+        true
     }
 
-    pub fn toggle_prestige(&self) {
-        self.toggle_task(TaskType::Prestige, &self.state.prestige_enabled);
+    fn prestige_pending(&self, tasks: &TasksConfig) -> bool {
+        let window = tasks.prestige_lookahead(self.task_manager.get_interval(TaskType::Upgrades));
+        let blocked = tasks.prestige_confirm_required
+            && !self.state.prestige_armed.load(Ordering::Relaxed);
+
+        self.is_task_enabled(TaskType::Prestige)
+            && !blocked
+            && self.task_manager.prestige_imminent(window)
     }
 
-    fn toggle_task(&self, task_type: TaskType, enabled: &AtomicBool) {
-        let was_enabled = enabled.fetch_xor(true, Ordering::Relaxed);
-        let (status, level) = if !was_enabled {
-            ("ENABLED", LogLevel::Success)
-        } else {
-            ("DISABLED", LogLevel::Error)
-        };
-        self.logger.log(level, &format!("{} {}", task_type.name(), status));
+    // Shortly after a prestige the spending tasks would only click unaffordable buttons
+    fn in_prestige_cooldown(&self, tasks: &TasksConfig) -> bool {
+        let cooldown = tasks.post_prestige_cooldown();
+        self.state
+            .last_prestige
+            .lock()
+            .is_some_and(|at| self.clock.elapsed(at) < cooldown)
     }
 
-    pub fn is_active(&self) -> bool {
-        self.state.active.load(Ordering::Relaxed)
+    // Whether prestige may run: armed, or confirmation isn't required
+    fn prestige_armed(&self) -> bool {
+        !self.profile().tasks.prestige_confirm_required
+            || self.state.prestige_armed.load(Ordering::Relaxed)
     }
 
-    pub fn is_task_enabled(&self, task_type: TaskType) -> bool {
-        match task_type {
-            TaskType::Upgrades => self.state.upgrades_enabled.load(Ordering::Relaxed),
-            TaskType::Souls => self.state.souls_enabled.load(Ordering::Relaxed),
-            TaskType::Prestige => self.state.prestige_enabled.load(Ordering::Relaxed),
+    // With confirmation required, a due prestige waits (logging once) until it has been armed
+    fn prestige_permitted(&self) -> bool {
+        if self.prestige_armed() {
+            return true;
+        }
+
+        let profile = self.profile();
+        if !self.state.prestige_ready_logged.swap(true, Ordering::Relaxed) {
+            self.logger.log(
+                LogLevel::Warning,
+                &format!(
+                    "Prestige is ready - press {} to arm it",
+                    profile.keybindings.arm_prestige
+                ),
+            );
         }
+        false
     }
 
-    pub fn get_stats(&self) -> Arc<Stats> {
-        self.stats.clone()
+    async fn run_task(&self, input: &mut dyn InputBackend, task_type: TaskType) {
+        let retry = &self.config.task_retry;
+        let mut retries = 0;
+        let failures_before = self.stats.get_failed_actions();
+
+        let outcome = loop {
+            let started = self.clock.now();
+            let clicks_before = self.stats.get_task_clicks();
+
+            let outcome = self.with_cursor_restore(input, task_type).await;
+
+            let clicks = self.stats.get_task_clicks().saturating_sub(clicks_before);
+            if self.stop_requested()
+                || !retry.should_retry(task_type, retries, self.clock.elapsed(started), clicks)
+            {
+                break outcome;
+            }
+
+            retries += 1;
+            self.logger.log(
+                LogLevel::Warning,
+                &format!(
+                    "{} looked ineffective, retrying ({}/{})",
+                    task_type.name(),
+                    retries,
+                    retry.max_retries
+                ),
+            );
+            tokio::time::sleep(retry.delay()).await;
+        };
+
+        self.stats.record_task_run(task_type, outcome);
+        let failed = self.stats.get_failed_actions() > failures_before;
+        let errors = self.task_manager.record_result(task_type, failed);
+        let limit = self.config.auto_disable.max_consecutive_errors;
+        if limit > 0 && errors >= limit {
+            self.state.enabled_flag(task_type).store(false, Ordering::Relaxed);
+            self.task_manager.record_result(task_type, false);
+            let message =
+                format!("{} DISABLED after {} consecutive errors", task_type.name(), errors);
+            self.logger.log(LogLevel::Error, &message);
+            self.notifier.notify(NotifyEvent::TaskDisabled, &message);
+        }
     }
 
-    pub fn get_logger(&self) -> Arc<Logger> {
-        self.logger.clone()
+    // Runs a task and puts the cursor back where it was so mining resumes cleanly
+    async fn with_cursor_restore(
+        &self,
+        input: &mut dyn InputBackend,
+        task_type: TaskType,
+    ) -> TaskOutcome {
+        let saved = input.location().ok();
+
+        let outcome = match task_type {
+            TaskType::Upgrades => self.perform_upgrades(input).await,
+            TaskType::Souls => self.perform_souls_upgrade(input).await,
+            TaskType::Relics => self.perform_relics_upgrade(input).await,
+            TaskType::Forge => self.perform_forge(input).await,
+            TaskType::Alchemy => self.perform_alchemy(input).await,
+            TaskType::Miner => self.perform_miner(input).await,
+            TaskType::Macro => self.perform_macro(input).await,
+            TaskType::Prestige => self.perform_prestige(input).await,
+        };
+
+        if let Some(target) = saved.or_else(|| self.resolve(self.profile().positions.mining)) {
+            let _ = self.move_to(input, target);
+            tokio::time::sleep(Timings::CLICK_DELAY).await;
+        }
+        outcome
     }
 
-    pub fn get_task_manager(&self) -> Arc<TaskManager> {
-        self.task_manager.clone()
+    // Succeeds when the affordability probe saw something to buy, fails when it saw nothing
+    async fn perform_upgrades(&self, input: &mut dyn InputBackend) -> TaskOutcome {
+        let profile = self.profile();
+        let positions = &profile.positions;
+        let delay = profile.tasks.click_delay(TaskType::Upgrades);
+        let settle = profile.tasks.panel_open_delay(TaskType::Upgrades);
+        self.logger.log(LogLevel::Task, "Running upgrades...");
+        
+        // Open upgrades panel
+        self.open_panel(input, positions.upgrade_icon_key, positions.upgrade_icon, delay, settle).await;
+        self.click_at(input, positions.upgrades_tab, delay).await;
+
+        let affordable = self.upgrades_affordable();
+        let outcome = match affordable {
+            Some(true) => TaskOutcome::Succeeded,
+            Some(false) => TaskOutcome::Failed,
+            None => TaskOutcome::Ran,
+        };
+        if !self.upgrades_worth_buying(&profile.tasks, affordable) {
+            return outcome;
+        }
+        
+        // Each pass buys one more level of every row and leaves the list scrolled back to the top
+        let scroll = self.config.scroll.upgrades_scroll_amount;
+        for _ in 0..profile.tasks.upgrade_passes.max(1) {
+            if self.stop_requested() {
+                break;
+            }
+
+            // Bottom-first buys the rows below the fold before the ones at the top, each list
+            // clicked in reverse so the cheapest upgrades go first
+            let bottom_first = profile.tasks.upgrades_bottom_first;
+            if bottom_first {
+                self.click_upgrade_rows_after_scroll(input, positions, scroll, delay, true).await;
+            }
+
+            // Click the rows visible before scrolling
+            let pause_at = profile.tasks.upgrade_pause_index(positions.upgrade_rows.len());
+            let rows: Vec<&ClickStep> = if bottom_first {
+                positions.upgrade_rows.iter().rev().collect()
+            } else {
+                positions.upgrade_rows.iter().collect()
+            };
+            for (i, step) in rows.into_iter().enumerate() {
+                if self.stop_requested() {
+                    break;
+                }
+                self.click_step(input, step, delay).await;
+                if i == pause_at {
+                    // Small pause mid-way to ensure clicks register
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                }
+            }
+
+            if !bottom_first {
+                self.click_upgrade_rows_after_scroll(input, positions, scroll, delay, false).await;
+            }
+        }
+
+        if self.stop_requested() {
+            self.abandon_panel(input, TaskType::Upgrades, delay).await;
+            return TaskOutcome::Ran;
+        }
+        if let Some(confirm) = positions.upgrades_confirm {
+            self.click_at(input, confirm, delay).await;
+        }
+        
+        self.logger.log(LogLevel::Success, "Upgrades complete");
+        outcome
     }
-}
\ No newline at end of file
+
+    // Scrolls the upgrade list down, clicks the rows revealed there and scrolls back to the top
+    async fn click_upgrade_rows_after_scroll(
+        &self,
+        input: &mut dyn InputBackend,
+        positions: &PositionsConfig,
+        scroll: i32,
+        delay: Duration,
+        reverse: bool,
+    ) {
+        if self.stop_requested() {
+            return;
+        }
+        self.scroll_at(input, positions.upgrades_scroll_area, -scroll).await;
+
+        // Positions have changed due to the scroll
+        let rows = &positions.upgrade_rows_after_scroll;
+        let rows: Vec<&ClickStep> =
+            if reverse { rows.iter().rev().collect() } else { rows.iter().collect() };
+        for step in rows {
+            if self.stop_requested() {
+                break;
+            }
+            self.click_step(input, step, delay).await;
+        }
+
+        // Scroll back even when stopping so the next run starts from the top
+        self.scroll_at(input, positions.upgrades_scroll_area, scroll).await;
+    }
+
+    async fn perform_souls_upgrade(&self, input: &mut dyn InputBackend) -> TaskOutcome {
+        let profile = self.profile();
+        let positions = &profile.positions;
+        let delay = profile.tasks.click_delay(TaskType::Souls);
+        let settle = profile.tasks.panel_open_delay(TaskType::Souls);
+        self.logger.log(LogLevel::Task, "Running souls upgrade...");
+        
+        // Open souls panel
+        self.open_panel(input, positions.upgrade_icon_key, positions.upgrade_icon, delay, settle).await;
+        self.click_at(input, positions.souls_tab, delay).await;
+        
+        // Click the rows visible before scrolling
+        for step in &positions.souls_rows {
+            if self.stop_requested() {
+                break;
+            }
+            self.click_step(input, step, delay).await;
+        }
+        
+        // Scroll down and click the rows revealed below
+        if !self.stop_requested() {
+            let scroll = self.config.scroll.souls_scroll_amount;
+            self.scroll_at(input, positions.souls_scroll_area, -scroll).await;
+            for step in &positions.souls_rows_after_scroll {
+                if self.stop_requested() {
+                    break;
+                }
+                self.click_step(input, step, delay).await;
+            }
+
+            // Reset scroll
+            self.scroll_at(input, positions.souls_scroll_area, scroll).await;
+        }
+
+        if self.stop_requested() {
+            self.abandon_panel(input, TaskType::Souls, delay).await;
+            return TaskOutcome::Ran;
+        }
+        if let Some(confirm) = positions.souls_confirm {
+            self.click_at(input, confirm, delay).await;
+        }
+        
+        self.logger.log(LogLevel::Success, "Souls upgrade complete");
+        TaskOutcome::Ran
+    }
+
+    // Leaves a task stopped part way, closing its panel when a close key is configured
+    async fn abandon_panel(
+        &self,
+        input: &mut dyn InputBackend,
+        task_type: TaskType,
+        delay: Duration,
+    ) {
+        if let Some(key) = self.profile().positions.panel_close_key {
+            self.press_key(input, key, delay).await;
+        }
+        self.logger.log(LogLevel::Warning, &format!("{} stopped part way", task_type.name()));
+    }
+
+    async fn perform_relics_upgrade(&self, input: &mut dyn InputBackend) -> TaskOutcome {
+        let profile = self.profile();
+        let positions = &profile.positions;
+        let delay = profile.tasks.click_delay(TaskType::Relics);
+        let settle = profile.tasks.panel_open_delay(TaskType::Relics);
+        self.logger.log(LogLevel::Task, "Running relics upgrade...");
+
+        // Open relics panel
+        self.open_panel(input, positions.upgrade_icon_key, positions.upgrade_icon, delay, settle).await;
+        self.click_at(input, positions.relics_tab, delay).await;
+
+        for step in &positions.relic_rows {
+            self.click_step(input, step, delay).await;
+        }
+
+        self.logger.log(LogLevel::Success, "Relics upgrade complete");
+        TaskOutcome::Ran
+    }
+
+    async fn perform_forge(&self, input: &mut dyn InputBackend) -> TaskOutcome {
+        let profile = self.profile();
+        let positions = &profile.positions;
+        let delay = profile.tasks.click_delay(TaskType::Forge);
+        let settle = profile.tasks.panel_open_delay(TaskType::Forge);
+        self.logger.log(LogLevel::Task, "Running forge...");
+
+        self.open_panel(input, positions.forge_icon_key, positions.forge_icon, delay, settle).await;
+        self.click_at(input, positions.forge_upgrade, delay).await;
+
+        self.logger.log(LogLevel::Success, "Forge complete");
+        TaskOutcome::Ran
+    }
+
+    async fn perform_alchemy(&self, input: &mut dyn InputBackend) -> TaskOutcome {
+        let profile = self.profile();
+        let positions = &profile.positions;
+        let delay = profile.tasks.click_delay(TaskType::Alchemy);
+        let settle = profile.tasks.panel_open_delay(TaskType::Alchemy);
+        self.logger.log(LogLevel::Task, "Running alchemy...");
+
+        // Open the forge panel, then its alchemy sub-tab
+        self.open_panel(input, positions.forge_icon_key, positions.forge_icon, delay, settle).await;
+        self.click_at(input, positions.alchemy_tab, delay).await;
+
+        for step in &positions.alchemy_rows {
+            self.click_step(input, step, delay).await;
+        }
+
+        self.logger.log(LogLevel::Success, "Alchemy complete");
+        TaskOutcome::Ran
+    }
+
+    async fn perform_miner(&self, input: &mut dyn InputBackend) -> TaskOutcome {
+        let profile = self.profile();
+        let positions = &profile.positions;
+        let delay = profile.tasks.click_delay(TaskType::Miner);
+        let settle = profile.tasks.panel_open_delay(TaskType::Miner);
+        self.logger.log(LogLevel::Task, "Running miner upgrades...");
+
+        let opened = self
+            .open_panel(input, positions.miner_icon_key, positions.miner_icon, delay, settle)
+            .await;
+        if !opened {
+            self.logger.log(
+                LogLevel::Warning,
+                "Miner needs positions.miner_icon or positions.miner_icon_key, skipping",
+            );
+            return TaskOutcome::Ran;
+        }
+
+        for step in &positions.miner_rows {
+            self.click_step(input, step, delay).await;
+        }
+
+        self.logger.log(LogLevel::Success, "Miner upgrades complete");
+        TaskOutcome::Ran
+    }
+
+    // Plays `tasks.macro_file`, re-read every run so edits apply without a restart
+    async fn perform_macro(&self, input: &mut dyn InputBackend) -> TaskOutcome {
+        let profile = self.profile();
+        let Some(path) = &profile.tasks.macro_file else {
+            self.logger.log(LogLevel::Warning, "Macro needs tasks.macro_file, skipping");
+            return TaskOutcome::Ran;
+        };
+        let recorded = match Macro::load_from_file(path) {
+            Ok(recorded) => recorded,
+            Err(e) => {
+                self.logger.log(LogLevel::Error, &format!("{:#}", e));
+                self.stats.increment_failed_actions();
+                return TaskOutcome::Ran;
+            }
+        };
+        self.logger.log(LogLevel::Task, &format!("Playing macro {}...", path));
+
+        self.play_macro(input, &recorded).await;
+        if self.stop_requested() {
+            self.logger.log(LogLevel::Warning, "Macro stopped part way");
+            return TaskOutcome::Ran;
+        }
+
+        self.logger.log(LogLevel::Success, "Macro complete");
+        TaskOutcome::Ran
+    }
+
+    async fn play_macro(&self, input: &mut dyn InputBackend, recorded: &Macro) {
+        let delay = self.profile().tasks.click_delay(TaskType::Macro);
+        for step in &recorded.steps {
+            tokio::time::sleep(step.delay()).await;
+            if self.stop_requested() {
+                break;
+            }
+            match &step.action {
+                MacroAction::Click(click) => self.click_step(input, click, delay).await,
+                MacroAction::Scroll { at, amount } => self.scroll_at(input, *at, *amount).await,
+            }
+        }
+    }
+
+    // Judged by the click checks of the steps that have `verify_at`
+    async fn perform_prestige(&self, input: &mut dyn InputBackend) -> TaskOutcome {
+        let profile = self.profile();
+        let positions = &profile.positions;
+        let delay = profile.tasks.click_delay(TaskType::Prestige);
+        self.logger.log(LogLevel::Task, "Running prestige...");
+        
+        let retries = self.config.pixel_check.click_retries;
+        let steps = &positions.prestige_steps;
+        let mut outcome = TaskOutcome::Ran;
+        for (i, step) in steps.iter().enumerate() {
+            match step.verify_at {
+                Some(verify) => {
+                    let clicked =
+                        self.click_at_verified(input, step.at, verify, retries, delay).await;
+                    outcome = outcome.and(clicked);
+                }
+                None => self.click_at(input, step.at, delay).await,
+            }
+
+            // Wait for the next step's button to show up, or just pause after the last one
+            match steps.get(i + 1) {
+                Some(next) => self.wait_until_ready(next.at, next.ready, step.wait()).await,
+                None => tokio::time::sleep(step.wait()).await,
+            }
+        }
+        
+        self.logger.log(LogLevel::Success, "Prestige complete");
+        self.notifier.notify(NotifyEvent::PrestigeComplete, "Prestige complete");
+        outcome
+    }
+
+    // Checks the open upgrades panel for anything affordable and adapts the upgrade interval.
+    // Without a configured probe (or pixel support) every cycle counts as worth running.
+    fn upgrades_worth_buying(&self, tasks: &TasksConfig, affordable: Option<bool>) -> bool {
+        let Some(affordable) = affordable else {
+            return true;
+        };
+        if affordable {
+            if self.task_manager.reset_backoff(TaskType::Upgrades) {
+                self.logger.log(LogLevel::Info, "Upgrades affordable again, back to normal interval");
+            }
+            return true;
+        }
+
+        let Some(cap) = tasks.upgrade_backoff_max() else {
+            return true;
+        };
+        let next = self.task_manager.back_off(TaskType::Upgrades, cap);
+        self.logger.log(
+            LogLevel::Info,
+            &format!("No upgrades affordable, next check in {}s", next.as_secs()),
+        );
+        false
+    }
+
+    // None when the probe isn't configured or this build can't read the screen
+    fn upgrades_affordable(&self) -> Option<bool> {
+        let check = &self.config.pixel_check;
+        let expected = check.upgrade_affordable.filter(|_| check.enabled && pixel::is_supported())?;
+        let pos = self.resolve(self.profile().positions.upgrade_affordable_probe?)?;
+        let color = pixel::sample(pos).ok()?;
+        Some(color.distance(expected) <= check.tolerance)
+    }
+
+    // Waits for `target` to show the expected color, falling back to the fixed `delay` when
+    // pixel checks are off, no color is configured, or the color never shows up
+    async fn wait_until_ready(&self, target: CoordMode, expected: Option<Rgb>, delay: Duration) {
+        let check = &self.config.pixel_check;
+        let Some(expected) = expected.filter(|_| check.enabled && pixel::is_supported()) else {
+            tokio::time::sleep(delay).await;
+            return;
+        };
+
+        if !self.wait_for_pixel(target, expected, check.tolerance, check.timeout()).await {
+            self.logger.log(LogLevel::Warning, "Pixel check timed out, using fixed delay");
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    // Polls the screen until the pixel at `target` is within `tolerance` of `expected`
+    async fn wait_for_pixel(
+        &self,
+        target: CoordMode,
+        expected: Rgb,
+        tolerance: u8,
+        timeout: Duration,
+    ) -> bool {
+        let Some(pos) = self.resolve(target) else {
+            return false;
+        };
+        let started = self.clock.now();
+
+        while self.clock.elapsed(started) < timeout {
+            if pixel::sample(pos).is_ok_and(|color| color.distance(expected) <= tolerance) {
+                return true;
+            }
+            tokio::time::sleep(self.config.pixel_check.poll()).await;
+        }
+        false
+    }
+
+    // Resolves a target to screen pixels, scaling pixel coordinates from the reference resolution
+    fn resolve(&self, target: CoordMode) -> Option<Position> {
+        let screen = *self.screen_size.read();
+        let pos = self.project(target, screen)?;
+        Some(if screen.is_some() { clamp_to_screen(pos, screen) } else { pos })
+    }
+
+    // Where a target lands on the given screen, before any clamping
+    fn project(&self, target: CoordMode, screen: Option<(i32, i32)>) -> Option<Position> {
+        let pos = target.resolve(screen)?;
+        Some(match (target, screen) {
+            (CoordMode::Pixels { .. }, Some(screen)) => {
+                self.config.resolution.scale_position(pos, screen)
+            }
+            _ => pos,
+        })
+    }
+
+    // Every configured target, across all profiles, that falls outside the screen
+    pub fn find_off_screen_positions(&self, screen: (i32, i32)) -> Vec<(String, Position)> {
+        let (width, height) = screen;
+        let mut offending = Vec::new();
+
+        for index in 0..self.profiles.len() {
+            let profile = self.profiles.get(index);
+            for (name, target) in profile.positions.named() {
+                let Some(pos) = self.project(target, Some(screen)) else {
+                    continue;
+                };
+                if !(0..width).contains(&pos.x) || !(0..height).contains(&pos.y) {
+                    let name = if self.profiles.len() > 1 {
+                        format!("{}: {}", profile.name, name)
+                    } else {
+                        name
+                    };
+                    offending.push((name, pos));
+                }
+            }
+        }
+        offending
+    }
+
+    fn report_off_screen_positions(&self, screen: (i32, i32)) {
+        for (name, pos) in self.find_off_screen_positions(screen) {
+            self.logger.log(
+                LogLevel::Error,
+                &format!(
+                    "Position {} at ({}, {}) is outside the {}x{} screen",
+                    name, pos.x, pos.y, screen.0, screen.1
+                ),
+            );
+        }
+    }
+
+    fn move_to(&self, input: &mut dyn InputBackend, pos: Position) -> Result<()> {
+        self.state.last_move_x.store(pos.x, Ordering::Relaxed);
+        self.state.last_move_y.store(pos.y, Ordering::Relaxed);
+        input.move_mouse(pos)
+    }
+
+    // Moves to a task target, gliding there when human-like movement is enabled
+    async fn travel_to(&self, input: &mut dyn InputBackend, to: Position) -> Result<()> {
+        let movement = &self.config.movement;
+        if !movement.humanlike || movement.steps < 2 {
+            return self.move_to(input, to);
+        }
+        let Ok(from) = input.location() else {
+            return self.move_to(input, to);
+        };
+        self.move_humanlike(input, from, to, movement.steps).await
+    }
+
+    // Follows a quadratic bezier with a randomly bowed control point, finishing exactly on `to`
+    async fn move_humanlike(
+        &self,
+        input: &mut dyn InputBackend,
+        from: Position,
+        to: Position,
+        steps: u32,
+    ) -> Result<()> {
+        let delta = to - from;
+        let bow = {
+            let mut rng = self.rng.lock();
+            rng.gen_range(-0.25..=0.25)
+        };
+        // Control point sits off the midpoint, perpendicular to the path
+        let control = (
+            (from.x + to.x) as f64 / 2.0 - delta.y as f64 * bow,
+            (from.y + to.y) as f64 / 2.0 + delta.x as f64 * bow,
+        );
+        let step_delay = self.config.movement.step_delay();
+
+        for step in 1..=steps {
+            let t = step as f64 / steps as f64;
+            let u = 1.0 - t;
+            let x = u * u * from.x as f64 + 2.0 * u * t * control.0 + t * t * to.x as f64;
+            let y = u * u * from.y as f64 + 2.0 * u * t * control.1 + t * t * to.y as f64;
+            let point = clamp_to_screen(
+                Position::new(x.round() as i32, y.round() as i32),
+                *self.screen_size.read(),
+            );
+            self.move_to(input, if step == steps { to } else { point })?;
+            tokio::time::sleep(step_delay).await;
+        }
+        Ok(())
+    }
+
+    // Checks a click against the configured safe area, warning when it falls outside
+    fn in_safe_area(&self, pos: Position) -> bool {
+        if self.config.safe_area.allows(pos) {
+            return true;
+        }
+        self.logger.log(
+            LogLevel::Warning,
+            &format!("Refused click at ({}, {}) outside the safe area", pos.x, pos.y),
+        );
+        false
+    }
+
+    // Where to click a target this time: anywhere inside a rectangle, or the point itself
+    fn pick_point(&self, target: CoordMode) -> CoordMode {
+        target.pick_within(&mut *self.rng.lock())
+    }
+
+    // Offsets a click target by up to `jitter` pixels, never going negative
+    fn apply_jitter(&self, pos: Position) -> Position {
+        let jitter = self.config.randomization.jitter;
+        if jitter <= 0 {
+            return pos;
+        }
+
+        let mut rng = self.rng.lock();
+        let pos = Position::new(
+            pos.x + rng.gen_range(-jitter..=jitter),
+            pos.y + rng.gen_range(-jitter..=jitter),
+        );
+        clamp_to_screen(pos, *self.screen_size.read())
+    }
+
+    async fn click_at(
+        &self,
+        input: &mut dyn InputBackend,
+        target: impl Into<CoordMode>,
+        delay: Duration,
+    ) {
+        self.click_at_with(input, target, Button::Left, 1, delay).await;
+    }
+
+    // Clicks `target`, then re-clicks up to `retries` times while the pixel at `expected_region`
+    // still shows its pre-click color. A single plain click, reported as `Ran`, when verification
+    // is unavailable.
+    async fn click_at_verified(
+        &self,
+        input: &mut dyn InputBackend,
+        target: CoordMode,
+        expected_region: CoordMode,
+        retries: u32,
+        delay: Duration,
+    ) -> TaskOutcome {
+        let check = &self.config.pixel_check;
+        let verify = check.enabled && pixel::is_supported() && retries > 0 && !self.dry_run;
+        let before = self
+            .resolve(expected_region)
+            .filter(|_| verify)
+            .and_then(|pos| Some((pos, pixel::sample(pos).ok()?)));
+        let Some((pos, before)) = before else {
+            self.click_at(input, target, delay).await;
+            return TaskOutcome::Ran;
+        };
+
+        for attempt in 0..=retries {
+            self.click_at(input, target, delay).await;
+            tokio::time::sleep(check.click_verify()).await;
+            if pixel::sample(pos).is_ok_and(|color| color.distance(before) > check.tolerance) {
+                return TaskOutcome::Succeeded;
+            }
+            if attempt < retries {
+                self.logger.log(
+                    LogLevel::Warning,
+                    &format!("Click didn't register, retrying ({}/{})", attempt + 1, retries),
+                );
+            }
+        }
+        self.logger.log(LogLevel::Error, "Click still didn't register after retries");
+        TaskOutcome::Failed
+    }
+
+    async fn click_step(&self, input: &mut dyn InputBackend, step: &ClickStep, delay: Duration) {
+        self.click_at_with(input, step.at, step.button, step.count, delay).await;
+    }
+
+    // Moves to the target once and clicks `count` times, so a count of 2 registers as a double-click
+    async fn click_at_with(
+        &self,
+        input: &mut dyn InputBackend,
+        target: impl Into<CoordMode>,
+        button: Button,
+        count: u32,
+        delay: Duration,
+    ) {
+        if self.is_shutting_down() {
+            return;
+        }
+        let Some(pos) = self.resolve(self.pick_point(target.into())) else {
+            self.stats.increment_failed_actions();
+            return;
+        };
+        let pos = self.apply_jitter(pos);
+        if !self.in_safe_area(pos) {
+            self.stats.increment_failed_actions();
+            return;
+        }
+        let moved = self.travel_to(input, pos).await.is_ok();
+        tokio::time::sleep(delay).await;
+
+        for _ in 0..count.max(1) {
+            let clicked = input.button(button, Direction::Click).is_ok();
+            if moved && clicked {
+                self.stats.increment_task_clicks();
+            } else {
+                self.stats.increment_failed_actions();
+            }
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    // Opens a panel with its game hotkey when one is configured, otherwise by clicking its icon,
+    // then waits `settle` for the panel animation to finish. Returns false, doing nothing, when
+    // the panel has neither.
+    async fn open_panel(
+        &self,
+        input: &mut dyn InputBackend,
+        key: Option<Key>,
+        icon: impl Into<Option<CoordMode>>,
+        delay: Duration,
+        settle: Duration,
+    ) -> bool {
+        match (key, icon.into()) {
+            (Some(key), _) => self.press_key(input, key, delay).await,
+            (None, Some(icon)) => self.click_at(input, icon, delay).await,
+            (None, None) => return false,
+        }
+        tokio::time::sleep(settle).await;
+        true
+    }
+
+    async fn press_key(&self, input: &mut dyn InputBackend, key: Key, delay: Duration) {
+        if self.is_shutting_down() {
+            return;
+        }
+        let pressed = input.key(key, Direction::Click).is_ok();
+        tokio::time::sleep(delay).await;
+
+        if pressed {
+            self.stats.increment_task_clicks();
+        } else {
+            self.stats.increment_failed_actions();
+        }
+    }
+
+    async fn scroll_at(&self, input: &mut dyn InputBackend, target: CoordMode, amount: i32) {
+        let Some(pos) = self.resolve(target) else {
+            self.stats.increment_failed_actions();
+            return;
+        };
+        if self.travel_to(input, pos).await.is_err() {
+            self.stats.increment_failed_actions();
+        }
+        tokio::time::sleep(Timings::SCROLL_DELAY).await;
+        
+        let notch = self.config.scroll.notch(amount);
+        for _ in 0..amount.abs() {
+            if input.scroll(notch).is_err() {
+                self.stats.increment_failed_actions();
+            }
+            tokio::time::sleep(Timings::POST_SCROLL_DELAY).await;
+        }
+    }
+
+    fn mining_delay(&self) -> Duration {
+        let since_start = self.clock.elapsed(*self.state.mining_started.read());
+        self.config.mining.ramped_delay(since_start)
+    }
+
+    // True once the running task should wrap up early, because the bot was paused or is exiting
+    fn stop_requested(&self) -> bool {
+        self.is_shutting_down() || self.state.stop_requested.load(Ordering::Relaxed)
+    }
+
+    // Public interface methods
+
+    // Pauses everything when anything is running, otherwise starts both mining and tasks
+    pub fn toggle(&self) {
+        let was = self.update_active(|bits| if bits == 0 { ALL_ACTIVE } else { 0 });
+        self.apply_active_change(was);
+    }
+
+    // Starts or stops mining on its own, leaving tasks as they are
+    pub fn toggle_mining(&self) {
+        let was = self.update_active(|bits| bits ^ MINING_ACTIVE);
+        self.apply_active_change(was);
+    }
+
+    // Starts or stops the scheduled tasks on their own, leaving mining as it is
+    pub fn toggle_tasks(&self) {
+        let was = self.update_active(|bits| bits ^ TASKS_ACTIVE);
+        self.apply_active_change(was);
+    }
+
+    // Swaps in the new activity bits, returning the old ones
+    fn update_active(&self, f: impl Fn(u8) -> u8) -> u8 {
+        self.state
+            .active
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| Some(f(bits)))
+            .unwrap_or_else(|bits| bits)
+    }
+
+    fn apply_active_change(&self, was: u8) {
+        let now = self.state.active.load(Ordering::Relaxed);
+        let started = now & !was;
+        let stopped = was & !now;
+
+        if was == 0 && now != 0 {
+            self.stats.reset();
+        }
+        if started & MINING_ACTIVE != 0 {
+            *self.state.mining_started.write() = self.clock.now();
+        }
+        if started & TASKS_ACTIVE != 0 {
+            // Resuming before a stopping task notices lets it carry on
+            self.state.stop_requested.store(false, Ordering::Relaxed);
+            self.task_manager.resume();
+            for task_type in TaskType::ALL {
+                if self.profile().tasks.get(task_type).run_on_start {
+                    self.task_manager.make_due(task_type);
+                }
+            }
+        }
+        if stopped & TASKS_ACTIVE != 0 {
+            if self.state.running_task.load(Ordering::Acquire) {
+                self.state.stop_requested.store(true, Ordering::Relaxed);
+            }
+            self.task_manager.pause();
+        }
+        if was != 0 && now == 0 {
+            self.stats.end_session();
+        }
+
+        let (message, level) = match (was, now) {
+            (0, 0) => return,
+            (0, ALL_ACTIVE) => ("Bot ACTIVATED".to_string(), LogLevel::Success),
+            (0, _) => (format!("Bot ACTIVATED ({})", activity_label(now)), LogLevel::Success),
+            (_, 0) => ("Bot PAUSED".to_string(), LogLevel::Warning),
+            _ if started & MINING_ACTIVE != 0 => ("Mining RESUMED".to_string(), LogLevel::Success),
+            _ if started & TASKS_ACTIVE != 0 => ("Tasks RESUMED".to_string(), LogLevel::Success),
+            _ if stopped & MINING_ACTIVE != 0 => ("Mining PAUSED".to_string(), LogLevel::Warning),
+            _ => ("Tasks PAUSED".to_string(), LogLevel::Warning),
+        };
+        if (was == 0) != (now == 0) {
+            *self.state.last_toggle.write() = self.clock.now();
+        }
+        self.logger.log(level, &message);
+
+        if self.stats.take_dirty() {
+            self.save_stats();
+        }
+    }
+
+    pub fn save_stats(&self) {
+        if !self.persist {
+            return;
+        }
+        if let Err(e) = self.stats.save_to_disk(STATS_PATH) {
+            self.logger.log(LogLevel::Error, &format!("Failed to save stats: {:#}", e));
+        }
+    }
+
+    pub fn toggle_upgrades(&self) {
+        self.toggle_task(TaskType::Upgrades, &self.state.upgrades_enabled);
+    }
+
+    pub fn toggle_souls(&self) {
+        self.toggle_task(TaskType::Souls, &self.state.souls_enabled);
+    }
+
+    pub fn toggle_relics(&self) {
+        self.toggle_task(TaskType::Relics, &self.state.relics_enabled);
+    }
+
+    pub fn toggle_forge(&self) {
+        self.toggle_task(TaskType::Forge, &self.state.forge_enabled);
+    }
+
+    pub fn toggle_alchemy(&self) {
+        self.toggle_task(TaskType::Alchemy, &self.state.alchemy_enabled);
+    }
+
+    pub fn toggle_miner(&self) {
+        self.toggle_task(TaskType::Miner, &self.state.miner_enabled);
+    }
+
+    pub fn toggle_macro(&self) {
+        self.toggle_task(TaskType::Macro, &self.state.macro_enabled);
+    }
+
+    // Turning prestige off is instant, but turning it on takes a second press within
+    // `PRESTIGE_ENABLE_WINDOW` so a stray key press can't enable it
+    pub fn toggle_prestige(&self) {
+        let flag = &self.state.prestige_enabled;
+        let first_press = self.state.prestige_enable_pending.lock().take();
+        let confirmed =
+            first_press.is_some_and(|at| self.clock.elapsed(at) <= Timings::PRESTIGE_ENABLE_WINDOW);
+
+        if flag.load(Ordering::Relaxed) || confirmed {
+            self.toggle_task(TaskType::Prestige, flag);
+            return;
+        }
+
+        *self.state.prestige_enable_pending.lock() = Some(self.clock.now());
+        self.logger.log(
+            LogLevel::Warning,
+            &format!(
+                "Press {} again to enable prestige",
+                self.profile().keybindings.prestige
+            ),
+        );
+    }
+
+    pub fn perform_action(&self, action: Action) {
+        match action {
+            Action::Toggle => self.toggle(),
+            Action::ToggleMining => self.toggle_mining(),
+            Action::ToggleTasks => self.toggle_tasks(),
+            Action::Upgrades => self.toggle_upgrades(),
+            Action::Souls => self.toggle_souls(),
+            Action::Prestige => self.toggle_prestige(),
+            Action::DumpInput => self.export_input_history(),
+            Action::Relics => self.toggle_relics(),
+            Action::Forge => self.toggle_forge(),
+            Action::Alchemy => self.toggle_alchemy(),
+            Action::ArmPrestige => self.arm_prestige(),
+            Action::NextProfile => self.next_profile(),
+            Action::ExportLog => self.export_log(),
+            Action::Miner => self.toggle_miner(),
+            Action::Macro => self.toggle_macro(),
+        }
+    }
+
+    // Like `perform_action`, but a task's key queues that task to run now instead of toggling it
+    pub fn perform_action_now(&self, action: Action) {
+        match action.task() {
+            Some(task_type) => self.run_now(task_type),
+            None => self.perform_action(action),
+        }
+    }
+
+    pub fn run_now(&self, task_type: TaskType) {
+        let mut forced = self.state.forced_tasks.lock();
+        if forced.contains(&task_type) {
+            return;
+        }
+        forced.push(task_type);
+        self.logger.log(LogLevel::Info, &format!("{} queued to run now", task_type.name()));
+    }
+
+    // Switches to the next profile; the new intervals start from a full cycle
+    pub fn next_profile(&self) {
+        if self.profiles.len() < 2 {
+            self.logger.log(LogLevel::Info, "No other profiles configured");
+            return;
+        }
+
+        let index = (self.profile_index.load(Ordering::Relaxed) + 1) % self.profiles.len();
+        self.profile_index.store(index, Ordering::Relaxed);
+        let profile = self.profiles.get(index);
+        *self.profile.write() = profile.clone();
+        self.task_manager.reconfigure(&profile.tasks);
+        self.state.prestige_armed.store(false, Ordering::Relaxed);
+        self.logger.log(LogLevel::Success, &format!("Switched to profile \"{}\"", profile.name));
+    }
+
+    pub fn profile(&self) -> Arc<Profile> {
+        self.profile.read().clone()
+    }
+
+    pub fn arm_prestige(&self) {
+        if self.state.prestige_armed.swap(true, Ordering::Relaxed) {
+            self.logger.log(LogLevel::Info, "Prestige is already armed");
+            return;
+        }
+        self.state.prestige_ready_logged.store(false, Ordering::Relaxed);
+        self.logger.log(LogLevel::Warning, "Prestige ARMED for one run");
+    }
+
+    // Sets a task directly, for remote control; skips prestige's double-press confirmation
+    pub fn set_task_enabled(&self, task_type: TaskType, enabled: bool) {
+        let flag = self.state.enabled_flag(task_type);
+        if flag.load(Ordering::Relaxed) != enabled {
+            self.toggle_task(task_type, flag);
+        }
+    }
+
+    fn toggle_task(&self, task_type: TaskType, enabled: &AtomicBool) {
+        let was_enabled = enabled.fetch_xor(true, Ordering::Relaxed);
+        let (status, level) = if !was_enabled {
+            ("ENABLED", LogLevel::Success)
+        } else {
+            ("DISABLED", LogLevel::Error)
+        };
+        self.logger.log(level, &format!("{} {}", task_type.name(), status));
+
+        // Saved right away so a crash still keeps the last choice
+        if !self.persist {
+            return;
+        }
+        if let Err(e) = self.state.save_toggles(STATE_PATH) {
+            self.logger.log(LogLevel::Error, &format!("Failed to save task toggles: {:#}", e));
+        }
+    }
+
+    pub fn last_commanded_position(&self) -> Position {
+        Position::new(
+            self.state.last_move_x.load(Ordering::Relaxed),
+            self.state.last_move_y.load(Ordering::Relaxed),
+        )
+    }
+
+    // Pauses the bot because the user grabbed the mouse
+    pub fn pause_for_user_input(&self) {
+        if !self.is_active() {
+            return;
+        }
+        self.logger.log(LogLevel::Warning, "Mouse movement detected, pausing");
+        self.toggle();
+    }
+
+    // Global panic key: pause right away and shut everything down
+    pub fn kill_switch(&self) {
+        if self.is_active() {
+            self.toggle();
+        }
+        self.logger.log(LogLevel::Error, "Kill switch pressed, shutting down");
+        self.shutdown();
+    }
+
+    // Asks the bot loop and input handler to stop; no further clicks are issued
+    pub fn shutdown(&self) {
+        self.state.shutdown.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.state.shutdown.load(Ordering::Relaxed)
+    }
+
+    pub fn get_screen_size(&self) -> Option<(i32, i32)> {
+        *self.screen_size.read()
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    // How long the bot has been in its current active or paused state
+    pub fn time_in_state(&self) -> Duration {
+        self.clock.elapsed(*self.state.last_toggle.read())
+    }
+
+    pub fn is_input_available(&self) -> bool {
+        !self.state.input_unavailable.load(Ordering::Relaxed)
+    }
+
+    // True while mining or tasks are running
+    pub fn is_active(&self) -> bool {
+        self.state.active.load(Ordering::Relaxed) != 0
+    }
+
+    pub fn is_mining_active(&self) -> bool {
+        self.state.active.load(Ordering::Relaxed) & MINING_ACTIVE != 0
+    }
+
+    pub fn is_tasks_active(&self) -> bool {
+        self.state.active.load(Ordering::Relaxed) & TASKS_ACTIVE != 0
+    }
+
+    pub fn is_task_enabled(&self, task_type: TaskType) -> bool {
+        self.state.enabled_flag(task_type).load(Ordering::Relaxed)
+    }
+
+    // The enabled task that comes due soonest, with how long until it does
+    pub fn next_due(&self) -> Option<(TaskType, Duration)> {
+        self.task_manager.next_due(|task_type| self.is_task_enabled(task_type))
+    }
+
+    pub fn snapshot(&self) -> BotSnapshot {
+        BotSnapshot {
+            active: self.is_active(),
+            mining_active: self.is_mining_active(),
+            tasks_active: self.is_tasks_active(),
+            input_available: self.is_input_available(),
+            clicks: self.stats.get_clicks(),
+            cpm: self.stats.get_cpm(),
+            runtime_secs: self.stats.get_runtime().as_secs(),
+            tasks: TaskType::ALL
+                .iter()
+                .map(|&task_type| TaskSnapshot {
+                    task: task_type,
+                    name: task_type.name(),
+                    enabled: self.is_task_enabled(task_type),
+                    secs_until_next: self.task_manager.get_time_until_next(task_type).as_secs(),
+                    period_secs: self.task_manager.get_period(task_type).as_secs(),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn export_input_history(&self) {
+        let path = format!("input_history_{}.log", Local::now().format("%Y%m%d_%H%M%S"));
+        match self.history.export(&path) {
+            Ok(count) => self
+                .logger
+                .log(LogLevel::Success, &format!("Exported {} input actions to {}", count, path)),
+            Err(e) => self
+                .logger
+                .log(LogLevel::Error, &format!("Failed to export input history: {}", e)),
+        }
+    }
+
+    pub fn export_log(&self) {
+        let format = self.config.logging.export_format;
+        let path = format!(
+            "activity_log_{}.{}",
+            Local::now().format("%Y%m%d_%H%M%S"),
+            format.extension()
+        );
+        match self.logger.export(&path, format) {
+            Ok(count) => self
+                .logger
+                .log(LogLevel::Success, &format!("Exported {} log entries to {}", count, path)),
+            Err(e) => self
+                .logger
+                .log(LogLevel::Error, &format!("Failed to export activity log: {:#}", e)),
+        }
+    }
+
+    pub fn get_config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn get_stats(&self) -> Arc<Stats<C>> {
+        self.stats.clone()
+    }
+
+    pub fn get_logger(&self) -> Arc<Logger> {
+        self.logger.clone()
+    }
+}
+// What runs when only one of mining and tasks is active
+fn activity_label(bits: u8) -> &'static str {
+    match bits {
+        MINING_ACTIVE => "mining only",
+        TASKS_ACTIVE => "tasks only",
+        _ => "",
+    }
+}
+
+// Highest priority first, or a random order when given an RNG. Prestige still goes first when
+// shuffled so nothing spends currency it's about to reset.
+fn order_due_tasks(due: &mut [TaskType], tasks: &TasksConfig, shuffle: Option<&mut StdRng>) {
+    match shuffle {
+        Some(rng) => {
+            due.shuffle(rng);
+            due.sort_by_key(|&task_type| task_type != TaskType::Prestige);
+        }
+        // Stable, so equal priorities keep their declaration order
+        None => due.sort_by_key(|&task_type| std::cmp::Reverse(tasks.priority(task_type))),
+    }
+}
+
+// Keeps a position non-negative and, when the screen size is known, on screen
+fn clamp_to_screen(pos: Position, screen: Option<(i32, i32)>) -> Position {
+    match screen {
+        Some((width, height)) => Position::new(
+            pos.x.clamp(0, (width - 1).max(0)),
+            pos.y.clamp(0, (height - 1).max(0)),
+        ),
+        None => Position::new(pos.x.max(0), pos.y.max(0)),
+    }
+}
+
+#[cfg(test)]
+impl Bot<crate::clock::FakeClock> {
+    // A bot on a fake clock with every task enabled, whatever toggles are saved on disk, that
+    // never writes stats or toggles back
+    pub fn for_test(config: Config, clock: &crate::clock::FakeClock) -> Self {
+        let mut bot = Bot::with_clock(config, false, clock.clone());
+        bot.persist = false;
+        for task_type in TaskType::ALL {
+            bot.state.enabled_flag(task_type).store(true, Ordering::Relaxed);
+        }
+        bot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{backend::NullBackend, clock::FakeClock, config::GamePositions};
+
+    const HOUR: Duration = Duration::from_secs(3600);
+
+    // Input that lands in the bot's history instead of on the real mouse
+    fn test_input(bot: &Bot<FakeClock>, fail_clicks: bool) -> RecordingBackend<NullBackend> {
+        let backend = NullBackend {
+            fail_clicks,
+            ..NullBackend::default()
+        };
+        RecordingBackend::new(backend, bot.history.clone())
+    }
+
+    fn paused_manager(clock: &FakeClock) -> TaskManager<FakeClock> {
+        let rng = Arc::new(Mutex::new(StdRng::seed_from_u64(0)));
+        TaskManager::new(&TasksConfig::default(), 0.0, rng, clock.clone())
+    }
+
+    fn manager(clock: &FakeClock) -> TaskManager<FakeClock> {
+        let manager = paused_manager(clock);
+        manager.resume();
+        manager
+    }
+
+    #[test]
+    fn starts_paused_with_nothing_due() {
+        let clock = FakeClock::new();
+        let manager = paused_manager(&clock);
+
+        assert!(manager.is_paused());
+        manager.make_due(TaskType::Upgrades);
+        assert!(!manager.should_run_task(TaskType::Upgrades));
+    }
+
+    #[test]
+    fn task_runs_once_its_interval_passes() {
+        let clock = FakeClock::new();
+        let manager = manager(&clock);
+        let interval = manager.get_interval(TaskType::Souls);
+
+        clock.advance(interval - Duration::from_millis(1));
+        assert!(!manager.should_run_task(TaskType::Souls));
+
+        clock.advance(Duration::from_millis(1));
+        assert!(manager.should_run_task(TaskType::Souls));
+    }
+
+    #[test]
+    fn update_last_run_starts_a_full_cycle() {
+        let clock = FakeClock::new();
+        let manager = manager(&clock);
+        manager.make_due(TaskType::Forge);
+        assert!(manager.should_run_task(TaskType::Forge));
+
+        manager.update_last_run(TaskType::Forge);
+        assert!(!manager.should_run_task(TaskType::Forge));
+        assert_eq!(
+            manager.get_time_until_next(TaskType::Forge),
+            manager.get_interval(TaskType::Forge)
+        );
+    }
+
+    #[test]
+    fn time_until_next_saturates_at_zero() {
+        let clock = FakeClock::new();
+        let manager = manager(&clock);
+
+        clock.advance(manager.get_interval(TaskType::Relics) + HOUR);
+        assert_eq!(manager.get_time_until_next(TaskType::Relics), Duration::ZERO);
+    }
+
+    #[test]
+    fn pause_freezes_countdown() {
+        let clock = FakeClock::new();
+        let manager = manager(&clock);
+        manager.set_interval(TaskType::Upgrades, HOUR);
+        manager.update_last_run(TaskType::Upgrades);
+
+        clock.advance(Duration::from_secs(600));
+        manager.pause();
+        let remaining = manager.get_time_until_next(TaskType::Upgrades);
+        assert_eq!(remaining, HOUR - Duration::from_secs(600));
+
+        clock.advance(HOUR * 2);
+        assert_eq!(manager.get_time_until_next(TaskType::Upgrades), remaining);
+        assert!(!manager.should_run_task(TaskType::Upgrades));
+
+        manager.resume();
+        assert_eq!(manager.get_time_until_next(TaskType::Upgrades), remaining);
+    }
+
+    #[test]
+    fn set_interval_moves_the_pending_deadline() {
+        let clock = FakeClock::new();
+        let manager = manager(&clock);
+        manager.set_interval(TaskType::Alchemy, HOUR);
+        manager.update_last_run(TaskType::Alchemy);
+
+        manager.set_interval(TaskType::Alchemy, HOUR / 2);
+        assert_eq!(manager.get_time_until_next(TaskType::Alchemy), HOUR / 2);
+        assert_eq!(manager.get_period(TaskType::Alchemy), HOUR / 2);
+    }
+
+    #[test]
+    fn next_due_skips_disabled_tasks() {
+        let clock = FakeClock::new();
+        let manager = manager(&clock);
+        for task_type in TaskType::ALL {
+            manager.set_interval(task_type, HOUR);
+            manager.update_last_run(task_type);
+        }
+        manager.set_interval(TaskType::Souls, Duration::from_secs(60));
+        manager.set_interval(TaskType::Forge, Duration::from_secs(120));
+        clock.advance(Duration::from_secs(30));
+
+        let next = manager.next_due(|_| true);
+        assert_eq!(next, Some((TaskType::Souls, Duration::from_secs(30))));
+        let next = manager.next_due(|task_type| task_type != TaskType::Souls);
+        assert_eq!(next, Some((TaskType::Forge, Duration::from_secs(90))));
+        assert_eq!(manager.next_due(|_| false), None);
+    }
+
+    #[test]
+    fn run_on_start_tasks_are_due_on_activation() {
+        let mut config = Config::default();
+        config.tasks.souls.run_on_start = true;
+        config.tasks.forge.run_on_start = true;
+        let bot = Bot::for_test(config, &FakeClock::new());
+        bot.toggle();
+
+        let manager = &bot.task_manager;
+        for task_type in TaskType::ALL {
+            if matches!(task_type, TaskType::Souls | TaskType::Forge) {
+                assert!(manager.should_run_task(task_type), "{}", task_type.name());
+            } else {
+                assert!(!manager.should_run_task(task_type), "{}", task_type.name());
+                let interval = manager.get_interval(task_type);
+                assert_eq!(manager.get_time_until_next(task_type), interval);
+            }
+        }
+    }
+
+    #[test]
+    fn prestige_imminent_within_window() {
+        let clock = FakeClock::new();
+        let manager = manager(&clock);
+        let interval = manager.get_interval(TaskType::Prestige);
+        clock.advance(interval - Duration::from_secs(30));
+
+        assert!(manager.prestige_imminent(Duration::from_secs(60)));
+        assert!(!manager.prestige_imminent(Duration::from_secs(10)));
+        assert!(!manager.prestige_imminent(Duration::ZERO));
+    }
+
+    #[test]
+    fn back_off_doubles_up_to_the_cap() {
+        let clock = FakeClock::new();
+        let manager = manager(&clock);
+        manager.set_interval(TaskType::Upgrades, Duration::from_secs(30));
+
+        assert_eq!(manager.back_off(TaskType::Upgrades, HOUR / 30), Duration::from_secs(60));
+        assert_eq!(manager.back_off(TaskType::Upgrades, HOUR / 30), Duration::from_secs(120));
+        assert_eq!(manager.back_off(TaskType::Upgrades, HOUR / 30), Duration::from_secs(120));
+
+        manager.update_last_run(TaskType::Upgrades);
+        assert_eq!(manager.get_period(TaskType::Upgrades), Duration::from_secs(120));
+
+        assert!(manager.reset_backoff(TaskType::Upgrades));
+        manager.update_last_run(TaskType::Upgrades);
+        assert_eq!(manager.get_period(TaskType::Upgrades), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn consecutive_errors_reset_on_success() {
+        let clock = FakeClock::new();
+        let manager = manager(&clock);
+        assert_eq!(manager.record_result(TaskType::Souls, true), 1);
+        assert_eq!(manager.record_result(TaskType::Souls, true), 2);
+        assert_eq!(manager.record_result(TaskType::Souls, false), 0);
+    }
+
+    // The last move before each scroll, i.e. where the cursor was hovering when it scrolled
+    fn scroll_positions(actions: &[String]) -> Vec<&str> {
+        let mut hovering = "";
+        let mut positions = Vec::new();
+        for action in actions {
+            if action.starts_with("move") {
+                hovering = action;
+            } else if action.starts_with("scroll") {
+                positions.push(hovering);
+            }
+        }
+        positions
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn each_panel_scrolls_over_its_own_area() {
+        let mut config = Config::default();
+        config.positions.upgrades_scroll_area = Position::new(400, 500).into();
+        config.positions.souls_scroll_area = Position::new(1400, 900).into();
+
+        for (task_type, area) in [
+            (TaskType::Upgrades, "move (400, 500)"),
+            (TaskType::Souls, "move (1400, 900)"),
+        ] {
+            let bot = Bot::for_test(config.clone(), &FakeClock::new());
+            bot.run_task(&mut test_input(&bot, false), task_type).await;
+            let actions = bot.history.descriptions();
+            let scrolled_at = scroll_positions(&actions);
+            assert!(!scrolled_at.is_empty(), "{} never scrolled", task_type.name());
+            assert!(scrolled_at.iter().all(|&at| at == area), "{}: {:?}", task_type.name(), scrolled_at);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn recording_backend_captures_a_task_in_order() {
+        let bot = Bot::for_test(Config::default(), &FakeClock::new());
+        let mut input = test_input(&bot, false);
+        input.move_mouse(GamePositions::MINING).unwrap();
+
+        bot.run_task(&mut input, TaskType::Forge).await;
+        assert_eq!(
+            bot.history.descriptions(),
+            [
+                "move (1855, 1335)",
+                "move (760, 1315)",
+                "left click",
+                "move (1280, 700)",
+                "left click",
+                "move (1855, 1335)",
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn confirm_click_ends_a_panel_task_unless_stopped() {
+        let mut config = Config::default();
+        config.positions.upgrades_confirm = Some(Position::new(900, 1100).into());
+        config.positions.souls_confirm = Some(Position::new(1300, 1100).into());
+
+        for (task_type, confirm) in [
+            (TaskType::Upgrades, "move (900, 1100)"),
+            (TaskType::Souls, "move (1300, 1100)"),
+        ] {
+            let bot = Bot::for_test(config.clone(), &FakeClock::new());
+            bot.run_task(&mut test_input(&bot, false), task_type).await;
+            let actions = bot.history.descriptions();
+            // The confirm click comes last, followed only by the move restoring the cursor
+            assert_eq!(actions[actions.len() - 3..], [confirm, "left click", "move (0, 0)"]);
+
+            let bot = Bot::for_test(config.clone(), &FakeClock::new());
+            bot.state.stop_requested.store(true, Ordering::Relaxed);
+            bot.run_task(&mut test_input(&bot, false), task_type).await;
+            let actions = bot.history.descriptions();
+            assert!(!actions.iter().any(|action| action == confirm), "{:?}", actions);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn saved_macro_replays_the_recorded_actions() {
+        let recorded = Macro {
+            steps: vec![
+                MacroStep {
+                    delay_ms: 350,
+                    action: MacroAction::Click(ClickStep {
+                        button: Button::Right,
+                        count: 2,
+                        ..Position::new(830, 300).into()
+                    }),
+                },
+                MacroStep {
+                    delay_ms: 200,
+                    action: MacroAction::Scroll {
+                        at: Position::new(900, 600).into(),
+                        amount: -2,
+                    },
+                },
+            ],
+        };
+        let path = std::env::temp_dir().join(format!("macro_replay_{}.toml", std::process::id()));
+        recorded.save_to_file(&path).unwrap();
+
+        // Straight from memory, as if just recorded
+        let live = Bot::for_test(Config::default(), &FakeClock::new());
+        live.play_macro(&mut test_input(&live, false), &recorded).await;
+
+        // Through the macro task, which loads the saved file
+        let mut config = Config::default();
+        config.tasks.macro_file = Some(path.display().to_string());
+        let replayed = Bot::for_test(config, &FakeClock::new());
+        replayed.run_task(&mut test_input(&replayed, false), TaskType::Macro).await;
+        let _ = std::fs::remove_file(&path);
+
+        let expected = [
+            "move (830, 300)",
+            "right click",
+            "right click",
+            "move (900, 600)",
+            "scroll 1",
+            "scroll 1",
+        ];
+        assert_eq!(live.history.descriptions(), expected);
+        // The task then puts the cursor back where it started
+        let mut with_restore = expected.to_vec();
+        with_restore.push("move (0, 0)");
+        assert_eq!(replayed.history.descriptions(), with_restore);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn tasks_started_together_never_interleave() {
+        let bot = Bot::for_test(Config::default(), &FakeClock::new());
+        let mut first = test_input(&bot, false);
+        let mut second = test_input(&bot, false);
+
+        // Alchemy tries to start while forge is waiting between its clicks
+        let (forge, alchemy) = tokio::join!(
+            bot.run_and_reschedule(&mut first, TaskType::Forge),
+            bot.run_and_reschedule(&mut second, TaskType::Alchemy),
+        );
+        assert!(forge && !alchemy);
+        let forge_actions = bot.history.descriptions();
+        assert_eq!(
+            forge_actions,
+            ["move (760, 1315)", "left click", "move (1280, 700)", "left click", "move (0, 0)"]
+        );
+
+        // Once the lock is free the other task runs, entirely after the first
+        assert!(bot.run_and_reschedule(&mut second, TaskType::Alchemy).await);
+        let actions = bot.history.descriptions();
+        assert_eq!(actions[..forge_actions.len()], forge_actions);
+        assert_eq!(actions[forge_actions.len()], "move (760, 1315)");
+        assert!(!bot.state.running_task.load(Ordering::Acquire));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn run_now_prestige_waits_for_arming() {
+        let mut config = Config::default();
+        config.tasks.prestige_confirm_required = true;
+        let clock = FakeClock::new();
+        let bot = Bot::for_test(config.clone(), &clock);
+        let mut input = test_input(&bot, false);
+        let mut focus = WindowFocus::with_clock(config.focus_guard.clone(), clock);
+
+        bot.run_now(TaskType::Prestige);
+        bot.run_forced_tasks(&mut input, &mut focus).await;
+        assert!(bot.history.descriptions().is_empty());
+
+        bot.arm_prestige();
+        bot.run_now(TaskType::Prestige);
+        bot.run_forced_tasks(&mut input, &mut focus).await;
+        assert!(bot.history.descriptions().contains(&"left click".to_string()));
+        // Arming is used up by the run
+        assert!(!bot.prestige_armed());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn spending_tasks_wait_out_the_post_prestige_cooldown() {
+        let mut config = Config::default();
+        config.tasks.post_prestige_cooldown_secs = 60;
+        let clock = FakeClock::new();
+        let bot = Bot::for_test(config, &clock);
+        let tasks = bot.profile().tasks.clone();
+        assert!(!bot.in_prestige_cooldown(&tasks));
+
+        assert!(bot.run_and_reschedule(&mut test_input(&bot, false), TaskType::Prestige).await);
+        assert!(bot.in_prestige_cooldown(&tasks));
+        clock.advance(Duration::from_secs(59));
+        assert!(bot.in_prestige_cooldown(&tasks));
+        clock.advance(Duration::from_secs(1));
+        assert!(!bot.in_prestige_cooldown(&tasks));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn consecutive_errors_disable_the_task_at_the_limit() {
+        let mut config = Config::default();
+        config.auto_disable.max_consecutive_errors = 3;
+        let bot = Bot::for_test(config, &FakeClock::new());
+        let mut input = test_input(&bot, true);
+
+        for _ in 0..2 {
+            bot.run_task(&mut input, TaskType::Forge).await;
+            assert!(bot.is_task_enabled(TaskType::Forge));
+        }
+        bot.run_task(&mut input, TaskType::Forge).await;
+        assert!(!bot.is_task_enabled(TaskType::Forge));
+        // The streak starts over for when the task is turned back on
+        assert_eq!(bot.task_manager.record_result(TaskType::Forge, true), 1);
+    }
+
+    #[test]
+    fn shuffled_task_order_varies_but_keeps_prestige_first() {
+        let tasks = TasksConfig::default();
+        let cycles = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            (0..10)
+                .map(|_| {
+                    let mut due = TaskType::ALL.to_vec();
+                    order_due_tasks(&mut due, &tasks, Some(&mut rng));
+                    due
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let orders = cycles(7);
+        assert!(orders.iter().all(|order| order[0] == TaskType::Prestige));
+        assert!(orders.iter().any(|order| *order != orders[0]));
+        assert_eq!(orders, cycles(7));
+    }
+}
@@ -0,0 +1,49 @@
+use std::time::{Duration, Instant};
+
+#[cfg(test)]
+use parking_lot::Mutex;
+#[cfg(test)]
+use std::sync::Arc;
+
+// Source of `Instant`s for timing logic, so tests can drive time by hand
+pub trait Clock: Clone + Send + Sync + 'static {
+    fn now(&self) -> Instant;
+
+    fn elapsed(&self, since: Instant) -> Duration {
+        self.now().saturating_duration_since(since)
+    }
+}
+
+// The real clock; a zero-sized wrapper around `Instant::now`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    #[inline]
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+// A clock that only moves when told to. Clones share the same time.
+#[cfg(test)]
+#[derive(Debug, Clone)]
+pub struct FakeClock(Arc<Mutex<Instant>>);
+
+#[cfg(test)]
+impl FakeClock {
+    pub fn new() -> Self {
+        Self(Arc::new(Mutex::new(Instant::now())))
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.0.lock() += by;
+    }
+}
+
+#[cfg(test)]
+impl Clock for FakeClock {
+    fn now(&self) -> Instant {
+        *self.0.lock()
+    }
+}
@@ -1,8 +1,447 @@
-use crate::types::Position;
-use std::time::Duration;
+use crate::{
+    logger::{ExportFormat, LogLevel},
+    pixel::Rgb,
+    types::{Action, ClickStep, CoordMode, Position, TaskType},
+};
+use anyhow::{bail, Context, Result};
+use chrono::NaiveTime;
+use device_query::Keycode;
+use enigo::{Button, Key};
+use ratatui::style::Color;
+use serde::{Deserialize, Serialize};
+use std::{fmt, fs, path::Path, str::FromStr, sync::Arc, time::Duration};
+use toml::{Table, Value};
 
 pub const APP_VERSION: &str = "2.0";
 pub const APP_NAME: &str = "IDLE CAVE MINER BOT";
+pub const CONFIG_PATH: &str = "config.toml";
+pub const STATS_PATH: &str = "stats.json";
+// Which tasks are toggled on, kept apart from the config since it's changed from the UI
+pub const STATE_PATH: &str = "state.json";
+pub const SESSIONS_DIR: &str = "sessions";
+// Bumped whenever a field moves or is renamed, with a matching entry in `MIGRATIONS`
+pub const CONFIG_VERSION: u32 = 2;
+
+// Target version, what changed, and the upgrade from the version before it
+type Migration = (u32, &'static str, fn(&mut Table));
+
+// Applied in order to the raw TOML
+const MIGRATIONS: [Migration; 1] = [(
+    2,
+    "prestige_button, prestige_claim and prestige_confirm moved into positions.prestige_steps",
+    migrate_prestige_steps,
+)];
+
+// User-tunable settings loaded from `config.toml`. Missing fields fall back to defaults.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    // Schema version; older files are migrated on load
+    pub version: u32,
+    // Start mining and tasks right away instead of waiting for the toggle key. A schedule, when
+    // set, decides instead.
+    pub start_active: bool,
+    pub mining: MiningConfig,
+    pub cpm_alarm: CpmAlarmConfig,
+    pub task_retry: TaskRetryConfig,
+    pub exit: ExitBehavior,
+    pub positions: PositionsConfig,
+    pub tasks: TasksConfig,
+    pub status_file: StatusFileConfig,
+    pub input_history: InputHistoryConfig,
+    pub auto_disable: AutoDisableConfig,
+    pub randomization: RandomizationConfig,
+    pub mouse_guard: MouseGuardConfig,
+    pub keybindings: Keybindings,
+    pub resolution: ResolutionConfig,
+    pub log_file: LogFileConfig,
+    pub movement: MovementConfig,
+    pub scroll: ScrollConfig,
+    pub pixel_check: PixelCheckConfig,
+    pub tuning: TuningConfig,
+    pub health_check: HealthCheckConfig,
+    pub logging: LoggingConfig,
+    pub schedule: ScheduleConfig,
+    pub limits: LimitsConfig,
+    pub display: DisplayConfig,
+    pub notify: NotifyConfig,
+    pub cpm_log: CpmLogConfig,
+    pub focus_guard: FocusGuardConfig,
+    pub battery: BatteryConfig,
+    pub control: ControlConfig,
+    pub safe_area: SafeAreaConfig,
+    // Extra profiles on top of the default one built from the sections above
+    pub profiles: Vec<Profile>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            version: CONFIG_VERSION,
+            start_active: false,
+            mining: MiningConfig::default(),
+            cpm_alarm: CpmAlarmConfig::default(),
+            task_retry: TaskRetryConfig::default(),
+            exit: ExitBehavior::default(),
+            positions: PositionsConfig::default(),
+            tasks: TasksConfig::default(),
+            status_file: StatusFileConfig::default(),
+            input_history: InputHistoryConfig::default(),
+            auto_disable: AutoDisableConfig::default(),
+            randomization: RandomizationConfig::default(),
+            mouse_guard: MouseGuardConfig::default(),
+            keybindings: Keybindings::default(),
+            resolution: ResolutionConfig::default(),
+            log_file: LogFileConfig::default(),
+            movement: MovementConfig::default(),
+            scroll: ScrollConfig::default(),
+            pixel_check: PixelCheckConfig::default(),
+            tuning: TuningConfig::default(),
+            health_check: HealthCheckConfig::default(),
+            logging: LoggingConfig::default(),
+            schedule: ScheduleConfig::default(),
+            limits: LimitsConfig::default(),
+            display: DisplayConfig::default(),
+            notify: NotifyConfig::default(),
+            cpm_log: CpmLogConfig::default(),
+            focus_guard: FocusGuardConfig::default(),
+            battery: BatteryConfig::default(),
+            control: ControlConfig::default(),
+            safe_area: SafeAreaConfig::default(),
+            profiles: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    pub fn load_from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut table: Table = toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        let migrated_from = migrate(&mut table)
+            .with_context(|| format!("Failed to load {}", path.display()))?;
+        let config: Self = Value::Table(table)
+            .try_into()
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        config
+            .validate()
+            .with_context(|| format!("Invalid config in {}", path.display()))?;
+
+        // Keep the original around, then rewrite the file in the current format
+        if let Some(from) = migrated_from {
+            let backup = format!("{}.v{}.bak", path.display(), from);
+            fs::copy(path, &backup).with_context(|| format!("Failed to back up to {}", backup))?;
+            config.save_to_file(path)?;
+            println!("Old config backed up to {}\n", backup);
+        }
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if !(1..=50).contains(&self.mining.cps) {
+            bail!("mining.cps must be between 1 and 50, got {}", self.mining.cps);
+        }
+        if self.mining.mode == MiningMode::Hold && self.mining.hold_ms == 0 {
+            bail!("mining.hold_ms must be above 0 in hold mode");
+        }
+        if matches!(
+            self.mining.button,
+            Button::ScrollUp | Button::ScrollDown | Button::ScrollLeft | Button::ScrollRight
+        ) {
+            bail!("mining.button must be a mouse button, got {:?}", self.mining.button);
+        }
+
+        for (name, amount) in [
+            ("scroll.upgrades_scroll_amount", self.scroll.upgrades_scroll_amount),
+            ("scroll.souls_scroll_amount", self.scroll.souls_scroll_amount),
+        ] {
+            if !(1..=20).contains(&amount) {
+                bail!("{} must be between 1 and 20, got {}", name, amount);
+            }
+        }
+
+        if !(16..=1000).contains(&self.display.tick_rate_ms) {
+            bail!(
+                "display.tick_rate_ms must be between 16 and 1000, got {}",
+                self.display.tick_rate_ms
+            );
+        }
+
+        if self.safe_area.enabled && (self.safe_area.width <= 0 || self.safe_area.height <= 0) {
+            bail!("safe_area width and height must be above 0");
+        }
+        if self.logging.log_retention == 0 {
+            bail!("logging.log_retention must be at least 1");
+        }
+
+        let res = &self.resolution;
+        if res.reference_width <= 0 || res.reference_height <= 0 {
+            bail!(
+                "resolution reference must be positive, got {}x{}",
+                res.reference_width,
+                res.reference_height
+            );
+        }
+
+        self.positions.validate().context("positions")?;
+        self.schedule.times().context("schedule")?;
+
+        self.keybindings.validate().context("keybindings")?;
+        for profile in &self.profiles {
+            if profile.name.trim().is_empty() {
+                bail!("every profile needs a name");
+            }
+            profile
+                .keybindings
+                .validate()
+                .with_context(|| format!("profile \"{}\" keybindings", profile.name))?;
+            profile
+                .positions
+                .validate()
+                .with_context(|| format!("profile \"{}\" positions", profile.name))?;
+        }
+        Ok(())
+    }
+
+    pub fn save_to_file(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let contents = toml::to_string_pretty(self)?;
+        fs::write(path, contents).with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+// Brings a parsed config up to `CONFIG_VERSION`, returning the version it started from when any
+// migration ran. Files from before versioning have no `version` and count as version 1.
+fn migrate(config: &mut Table) -> Result<Option<u32>> {
+    let version = match config.get("version") {
+        None => 1,
+        Some(value) => value
+            .as_integer()
+            .and_then(|version| u32::try_from(version).ok())
+            .context("version must be a positive whole number")?,
+    };
+    if version > CONFIG_VERSION {
+        bail!(
+            "config version {} is newer than this build supports (up to {}); update the bot",
+            version,
+            CONFIG_VERSION
+        );
+    }
+    if version == CONFIG_VERSION {
+        return Ok(None);
+    }
+
+    for (to, description, migration) in MIGRATIONS {
+        if version < to {
+            migration(config);
+            println!("Migrated config to version {}: {}", to, description);
+        }
+    }
+    config.insert("version".to_string(), Value::Integer(CONFIG_VERSION.into()));
+    Ok(Some(version))
+}
+
+// Version 2: the three prestige positions and their pixel colors became a list of steps, in the
+// top-level positions and every profile
+fn migrate_prestige_steps(config: &mut Table) {
+    let mut colors = [None, None, None];
+    if let Some(pixel_check) = config.get_mut("pixel_check").and_then(Value::as_table_mut) {
+        colors[1] = pixel_check.remove("prestige_claim");
+        colors[2] = pixel_check.remove("prestige_confirm");
+    }
+
+    if let Some(positions) = config.get_mut("positions").and_then(Value::as_table_mut) {
+        migrate_prestige_positions(positions, colors);
+    }
+    if let Some(profiles) = config.get_mut("profiles").and_then(Value::as_array_mut) {
+        for profile in profiles {
+            if let Some(positions) = profile.get_mut("positions").and_then(Value::as_table_mut) {
+                migrate_prestige_positions(positions, [None, None, None]);
+            }
+        }
+    }
+}
+
+// Moved positions and colors are written over the default steps
+fn migrate_prestige_positions(positions: &mut Table, colors: [Option<Value>; 3]) {
+    let moved =
+        ["prestige_button", "prestige_claim", "prestige_confirm"].map(|key| positions.remove(key));
+    positions.remove("prestige_confirm_verify");
+    if moved.iter().chain(&colors).all(Option::is_none) {
+        return;
+    }
+
+    let Ok(Value::Array(mut steps)) = Value::try_from(PositionsConfig::default().prestige_steps)
+    else {
+        return;
+    };
+    for ((step, at), ready) in steps.iter_mut().zip(moved).zip(colors) {
+        let Some(step) = step.as_table_mut() else {
+            continue;
+        };
+        if let Some(Value::Table(at)) = at {
+            for key in ["x", "y", "xp", "yp"] {
+                step.remove(key);
+            }
+            // The confirm click is verified against its own position
+            if step.contains_key("verify_at") {
+                step.insert("verify_at".to_string(), Value::Table(at.clone()));
+            }
+            step.extend(at);
+        }
+        if let Some(ready) = ready {
+            step.insert("ready".to_string(), ready);
+        }
+    }
+    positions.insert("prestige_steps".to_string(), Value::Array(steps));
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MiningConfig {
+    // Mining clicks per second, between 1 and 50
+    pub cps: u32,
+    // `click` taps the mining spot; `hold` presses it for `hold_ms` for charge-up buttons
+    pub mode: MiningMode,
+    pub hold_ms: u64,
+    // Mouse button that mines: `Left`, `Right`, `Middle`, `Back` or `Forward`
+    pub button: Button,
+    // Ease from a slow start up to `cps` over this long after each activation; 0 starts at full
+    // speed
+    pub ramp_ms: u64,
+}
+
+impl MiningConfig {
+    pub fn delay(&self) -> Duration {
+        Duration::from_millis(1000 / u64::from(self.cps.max(1)))
+    }
+
+    pub fn hold(&self) -> Duration {
+        Duration::from_millis(self.hold_ms)
+    }
+
+    // Mining delay `elapsed` after activation, shrinking linearly to `delay()` over the ramp
+    pub fn ramped_delay(&self, elapsed: Duration) -> Duration {
+        let delay = self.delay();
+        let ramp = Duration::from_millis(self.ramp_ms);
+        if elapsed >= ramp {
+            return delay;
+        }
+        let start = Timings::MINING_RAMP_START.max(delay);
+        let progress = elapsed.as_secs_f64() / ramp.as_secs_f64();
+        start - (start - delay).mul_f64(progress)
+    }
+}
+
+impl Default for MiningConfig {
+    fn default() -> Self {
+        Self {
+            cps: (1000 / Timings::MINING_DELAY.as_millis()) as u32,
+            mode: MiningMode::Click,
+            hold_ms: 1000,
+            button: Button::Left,
+            ramp_ms: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MiningMode {
+    #[default]
+    Click,
+    Hold,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CpmAlarmConfig {
+    // Alarm when CPM stays below this value; 0 disables the alarm
+    pub min_cpm: u64,
+    // How long CPM must stay below the floor before alarming
+    pub sustain_secs: u64,
+    // Ring the terminal bell when the alarm fires
+    pub bell: bool,
+}
+
+impl CpmAlarmConfig {
+    pub fn sustain(&self) -> Duration {
+        Duration::from_secs(self.sustain_secs)
+    }
+}
+
+impl Default for CpmAlarmConfig {
+    fn default() -> Self {
+        Self {
+            min_cpm: 0,
+            sustain_secs: 60,
+            bell: true,
+        }
+    }
+}
+
+// Pauses the bot when a region that should keep changing (like the resource counter) freezes.
+// Needs the `pixel-check` build feature.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HealthCheckConfig {
+    pub enabled: bool,
+    // Top-left corner of the watched region
+    pub region: CoordMode,
+    pub width: u32,
+    pub height: u32,
+    pub interval_secs: u64,
+    // Identical captures in a row before the game is treated as frozen
+    pub max_unchanged: u32,
+}
+
+impl HealthCheckConfig {
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs)
+    }
+}
+
+impl Default for HealthCheckConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            region: GamePositions::RESOURCE_COUNTER.into(),
+            width: 240,
+            height: 60,
+            interval_secs: 30,
+            max_unchanged: 4,
+        }
+    }
+}
+
+// Local wall-clock times, e.g. `start = "22:00"`, `end = "06:30"`. The bot activates when the
+// clock reaches `start` and pauses at `end`; either may be left out.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScheduleConfig {
+    pub start: Option<String>,
+    pub end: Option<String>,
+}
+
+impl ScheduleConfig {
+    pub fn times(&self) -> Result<(Option<NaiveTime>, Option<NaiveTime>)> {
+        let parse = |time: &Option<String>, name: &str| {
+            time.as_deref()
+                .map(|time| {
+                    NaiveTime::parse_from_str(time, "%H:%M")
+                        .with_context(|| format!("{} must be HH:MM, got {:?}", name, time))
+                })
+                .transpose()
+        };
+        Ok((parse(&self.start, "start")?, parse(&self.end, "end")?))
+    }
+}
 
 pub struct GamePositions;
 
@@ -11,15 +450,1291 @@ impl GamePositions {
     pub const UPGRADE_ICON: Position = Position::new(570, 1315);
     pub const UPGRADES_TAB: Position = Position::new(200, 1200);
     pub const SOULS_TAB: Position = Position::new(575, 1200);
+    pub const RELICS_TAB: Position = Position::new(950, 1200);
+    pub const FORGE_ICON: Position = Position::new(760, 1315);
+    pub const FORGE_UPGRADE: Position = Position::new(1280, 700);
+    pub const ALCHEMY_TAB: Position = Position::new(575, 1200);
     pub const SAFE_SCROLL_AREA: Position = Position::new(1030, 630);
     pub const PRESTIGE_BUTTON: Position = Position::new(1200, 245);
     pub const PRESTIGE_CLAIM: Position = Position::new(1850, 1115);
     pub const PRESTIGE_CONFIRM: Position = Position::new(1285, 860);
+    pub const RESOURCE_COUNTER: Position = Position::new(840, 40);
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TaskRetryConfig {
+    pub enabled: bool,
+    // Retries allowed per task run after the first attempt
+    pub max_retries: u32,
+    pub delay_ms: u64,
+    // A run finishing faster than this is treated as having done nothing
+    pub min_duration_ms: u64,
+}
+
+impl TaskRetryConfig {
+    pub fn delay(&self) -> Duration {
+        Duration::from_millis(self.delay_ms)
+    }
+
+    pub fn looks_ineffective(&self, duration: Duration, clicks: u64) -> bool {
+        clicks == 0 || duration < Duration::from_millis(self.min_duration_ms)
+    }
+
+    // Whether a run gets another attempt after `retries` so far. Never for prestige, where a
+    // second attempt would be a second reset rather than a retry.
+    pub fn should_retry(
+        &self,
+        task_type: TaskType,
+        retries: u32,
+        duration: Duration,
+        clicks: u64,
+    ) -> bool {
+        self.enabled
+            && task_type != TaskType::Prestige
+            && retries < self.max_retries
+            && self.looks_ineffective(duration, clicks)
+    }
+}
+
+impl Default for TaskRetryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_retries: 1,
+            delay_ms: 500,
+            min_duration_ms: 250,
+        }
+    }
+}
+
+// What happens when the user quits
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExitBehavior {
+    // Write the active config back to disk
+    pub save_config: bool,
+    // Print a session summary after the terminal is restored
+    pub print_summary: bool,
+    // Ask for confirmation before quitting
+    pub confirm: bool,
+    // Without `confirm`, ESC has to be pressed twice within this many milliseconds to quit;
+    // 0 quits on the first press
+    pub double_tap_ms: u64,
+}
+
+impl ExitBehavior {
+    pub fn double_tap(&self) -> Option<Duration> {
+        (self.double_tap_ms > 0).then(|| Duration::from_millis(self.double_tap_ms))
+    }
+}
+
+impl Default for ExitBehavior {
+    fn default() -> Self {
+        Self {
+            save_config: false,
+            print_summary: false,
+            confirm: false,
+            double_tap_ms: 1000,
+        }
+    }
+}
+
+// Overridable game positions; each entry is `{ x = .., y = .. }` or `{ xp = .., yp = .. }`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PositionsConfig {
+    pub mining: CoordMode,
+    pub upgrade_icon: CoordMode,
+    pub upgrades_tab: CoordMode,
+    pub souls_tab: CoordMode,
+    pub relics_tab: CoordMode,
+    // Panel rows clicked in order; the `_after_scroll` lists are clicked once the panel is
+    // scrolled down
+    pub upgrade_rows: Vec<ClickStep>,
+    pub upgrade_rows_after_scroll: Vec<ClickStep>,
+    pub souls_rows: Vec<ClickStep>,
+    pub souls_rows_after_scroll: Vec<ClickStep>,
+    pub relic_rows: Vec<ClickStep>,
+    pub forge_icon: CoordMode,
+    pub forge_upgrade: CoordMode,
+    // Game hotkeys pressed instead of clicking the panel icons, e.g. `"F2"` or `{ Unicode = "u" }`
+    pub upgrade_icon_key: Option<Key>,
+    pub forge_icon_key: Option<Key>,
+    // Game hotkey that closes the open panel, pressed when a task is stopped part way
+    pub panel_close_key: Option<Key>,
+    // Alchemy lives in a sub-tab of the forge panel
+    pub alchemy_tab: CoordMode,
+    pub alchemy_rows: Vec<ClickStep>,
+    // The miner panel has no built-in position; the miner task needs the icon or its key set
+    pub miner_icon: Option<CoordMode>,
+    pub miner_icon_key: Option<Key>,
+    pub miner_rows: Vec<ClickStep>,
+    // Where the cursor hovers while scrolling each panel
+    pub upgrades_scroll_area: CoordMode,
+    pub souls_scroll_area: CoordMode,
+    // Clicked in order by the prestige task
+    pub prestige_steps: Vec<PrestigeStep>,
+    // Optional "confirm purchase" clicks made after all rows of a panel
+    pub upgrades_confirm: Option<CoordMode>,
+    pub souls_confirm: Option<CoordMode>,
+    // Pixel in the open upgrades panel that shows `pixel_check.upgrade_affordable` when
+    // something can be bought
+    pub upgrade_affordable_probe: Option<CoordMode>,
+}
+
+impl PositionsConfig {
+    fn validate(&self) -> Result<()> {
+        for (name, target) in self.named() {
+            if target.has_negative_size() {
+                bail!("{} can't have a negative width or height", name);
+            }
+            if target.overruns_screen() {
+                bail!("{} reaches past the edge of the screen", name);
+            }
+        }
+        Ok(())
+    }
+
+    // Every click target with a readable name, including the built-in panel rows
+    pub fn named(&self) -> Vec<(String, CoordMode)> {
+        let mut named = vec![
+            ("mining".to_string(), self.mining),
+            ("upgrade_icon".to_string(), self.upgrade_icon),
+            ("upgrades_tab".to_string(), self.upgrades_tab),
+            ("souls_tab".to_string(), self.souls_tab),
+            ("relics_tab".to_string(), self.relics_tab),
+            ("forge_icon".to_string(), self.forge_icon),
+            ("forge_upgrade".to_string(), self.forge_upgrade),
+            ("alchemy_tab".to_string(), self.alchemy_tab),
+            ("upgrades_scroll_area".to_string(), self.upgrades_scroll_area),
+            ("souls_scroll_area".to_string(), self.souls_scroll_area),
+        ];
+        if let Some(confirm) = self.upgrades_confirm {
+            named.push(("upgrades_confirm".to_string(), confirm));
+        }
+        if let Some(confirm) = self.souls_confirm {
+            named.push(("souls_confirm".to_string(), confirm));
+        }
+        if let Some(probe) = self.upgrade_affordable_probe {
+            named.push(("upgrade_affordable_probe".to_string(), probe));
+        }
+        for (i, step) in self.prestige_steps.iter().enumerate() {
+            named.push((format!("prestige_steps[{}]", i), step.at));
+            if let Some(verify) = step.verify_at {
+                named.push((format!("prestige_steps[{}].verify_at", i), verify));
+            }
+        }
+        for (i, row) in self.relic_rows.iter().enumerate() {
+            named.push((format!("relic_rows[{}]", i), row.at));
+        }
+        for (i, row) in self.alchemy_rows.iter().enumerate() {
+            named.push((format!("alchemy_rows[{}]", i), row.at));
+        }
+        if let Some(icon) = self.miner_icon {
+            named.push(("miner_icon".to_string(), icon));
+        }
+        for (i, row) in self.miner_rows.iter().enumerate() {
+            named.push((format!("miner_rows[{}]", i), row.at));
+        }
+        for (list, rows) in [
+            ("upgrade_rows", &self.upgrade_rows),
+            ("upgrade_rows_after_scroll", &self.upgrade_rows_after_scroll),
+            ("souls_rows", &self.souls_rows),
+            ("souls_rows_after_scroll", &self.souls_rows_after_scroll),
+        ] {
+            for (i, row) in rows.iter().enumerate() {
+                named.push((format!("{}[{}]", list, i), row.at));
+            }
+        }
+        named
+    }
+}
+
+impl Default for PositionsConfig {
+    fn default() -> Self {
+        Self {
+            mining: GamePositions::MINING.into(),
+            upgrade_icon: GamePositions::UPGRADE_ICON.into(),
+            upgrades_tab: GamePositions::UPGRADES_TAB.into(),
+            souls_tab: GamePositions::SOULS_TAB.into(),
+            relics_tab: GamePositions::RELICS_TAB.into(),
+            upgrade_rows: UpgradePositions::BEFORE_SCROLL.iter().map(|&pos| pos.into()).collect(),
+            upgrade_rows_after_scroll: UpgradePositions::AFTER_SCROLL
+                .iter()
+                .map(|&pos| pos.into())
+                .collect(),
+            souls_rows: SoulsPositions::BEFORE_SCROLL.iter().map(|&pos| pos.into()).collect(),
+            souls_rows_after_scroll: vec![SoulsPositions::AFTER_SCROLL.into()],
+            relic_rows: RelicPositions::ROWS.iter().map(|&pos| pos.into()).collect(),
+            forge_icon: GamePositions::FORGE_ICON.into(),
+            forge_upgrade: GamePositions::FORGE_UPGRADE.into(),
+            upgrade_icon_key: None,
+            forge_icon_key: None,
+            panel_close_key: None,
+            alchemy_tab: GamePositions::ALCHEMY_TAB.into(),
+            alchemy_rows: AlchemyPositions::ROWS.iter().map(|&pos| pos.into()).collect(),
+            miner_icon: None,
+            miner_icon_key: None,
+            miner_rows: Vec::new(),
+            upgrades_scroll_area: GamePositions::SAFE_SCROLL_AREA.into(),
+            souls_scroll_area: GamePositions::SAFE_SCROLL_AREA.into(),
+            prestige_steps: vec![
+                PrestigeStep::new(GamePositions::PRESTIGE_BUTTON, Timings::PRESTIGE_WAIT),
+                PrestigeStep::new(GamePositions::PRESTIGE_CLAIM, Timings::PRESTIGE_WAIT),
+                // A missed confirm leaves the prestige dialog open, so make sure this one lands
+                PrestigeStep {
+                    verify_at: Some(GamePositions::PRESTIGE_CONFIRM.into()),
+                    ..PrestigeStep::new(
+                        GamePositions::PRESTIGE_CONFIRM,
+                        Timings::PRESTIGE_COMPLETE_WAIT,
+                    )
+                },
+            ],
+            upgrades_confirm: None,
+            souls_confirm: None,
+            upgrade_affordable_probe: None,
+        }
+    }
+}
+
+// One click of the prestige sequence, e.g. `{ x = 1200, y = 245, wait_ms = 1000 }`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PrestigeStep {
+    #[serde(flatten)]
+    pub at: CoordMode,
+    // Pause after the click. When the next step has a `ready` color this is the fallback used
+    // if the color never shows up.
+    #[serde(default = "PrestigeStep::default_wait_ms")]
+    pub wait_ms: u64,
+    // Color `at` shows once it can be clicked, waited for after the previous step; needs
+    // `pixel_check.enabled`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ready: Option<Rgb>,
+    // Pixel that changes once the click lands; the click is retried until it does
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verify_at: Option<CoordMode>,
+}
+
+impl PrestigeStep {
+    fn new(pos: Position, wait: Duration) -> Self {
+        Self {
+            at: pos.into(),
+            wait_ms: wait.as_millis() as u64,
+            ready: None,
+            verify_at: None,
+        }
+    }
+
+    fn default_wait_ms() -> u64 {
+        Timings::PRESTIGE_WAIT.as_millis() as u64
+    }
+
+    pub fn wait(&self) -> Duration {
+        Duration::from_millis(self.wait_ms)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TasksConfig {
+    pub upgrades: TaskConfig,
+    pub souls: TaskConfig,
+    pub relics: TaskConfig,
+    pub forge: TaskConfig,
+    pub alchemy: TaskConfig,
+    pub miner: TaskConfig,
+    #[serde(rename = "macro")]
+    pub macro_task: TaskConfig,
+    pub prestige: TaskConfig,
+    // Macro file the macro task plays, as saved by `--record-macro`; the task skips until set
+    pub macro_file: Option<String>,
+    // Hold prestige until it is armed with a key press; one arm allows one run
+    pub prestige_confirm_required: bool,
+    // Skip upgrades, souls and relics when prestige is due within this many seconds; defaults
+    // to one upgrade interval, 0 turns it off
+    pub prestige_lookahead_secs: Option<u64>,
+    // Hold upgrades, souls and relics for this many seconds after a prestige, while nothing is
+    // affordable yet; 0 turns it off
+    pub post_prestige_cooldown_secs: u64,
+    // Times the upgrade rows are clicked per cycle, buying one level per pass
+    pub upgrade_passes: u32,
+    // Where in the upgrade rows to pause briefly so clicks register, as a fraction of the list
+    pub upgrade_pause_at: f64,
+    // Click the rows below the scroll first, bottom to top, for layouts with the cheapest
+    // upgrades at the bottom
+    pub upgrades_bottom_first: bool,
+    // When the affordability check finds nothing to buy, double the upgrade interval up to this
+    // many seconds; a purchase resets it. Unset keeps the interval fixed.
+    pub upgrade_backoff_max_secs: Option<u64>,
+}
+
+impl Default for TasksConfig {
+    fn default() -> Self {
+        Self {
+            upgrades: TaskConfig::default(),
+            souls: TaskConfig::default(),
+            relics: TaskConfig::default(),
+            forge: TaskConfig::default(),
+            alchemy: TaskConfig::default(),
+            miner: TaskConfig::default(),
+            macro_task: TaskConfig::default(),
+            prestige: TaskConfig::default(),
+            macro_file: None,
+            prestige_confirm_required: false,
+            prestige_lookahead_secs: None,
+            post_prestige_cooldown_secs: 0,
+            upgrade_passes: 1,
+            upgrade_pause_at: 0.5,
+            upgrades_bottom_first: false,
+            upgrade_backoff_max_secs: None,
+        }
+    }
+}
+
+impl TasksConfig {
+    pub fn get(&self, task_type: TaskType) -> &TaskConfig {
+        match task_type {
+            TaskType::Upgrades => &self.upgrades,
+            TaskType::Souls => &self.souls,
+            TaskType::Relics => &self.relics,
+            TaskType::Forge => &self.forge,
+            TaskType::Alchemy => &self.alchemy,
+            TaskType::Miner => &self.miner,
+            TaskType::Macro => &self.macro_task,
+            TaskType::Prestige => &self.prestige,
+        }
+    }
+
+    pub fn interval(&self, task_type: TaskType) -> Duration {
+        self.get(task_type)
+            .interval_secs
+            .map(Duration::from_secs)
+            .unwrap_or(match task_type {
+                TaskType::Upgrades => Timings::UPGRADE_INTERVAL,
+                TaskType::Souls => Timings::SOULS_INTERVAL,
+                TaskType::Relics => Timings::RELICS_INTERVAL,
+                TaskType::Forge => Timings::FORGE_INTERVAL,
+                TaskType::Alchemy => Timings::ALCHEMY_INTERVAL,
+                TaskType::Miner => Timings::MINER_INTERVAL,
+                TaskType::Macro => Timings::MACRO_INTERVAL,
+                TaskType::Prestige => Timings::PRESTIGE_INTERVAL,
+            })
+    }
+
+    pub fn post_prestige_cooldown(&self) -> Duration {
+        Duration::from_secs(self.post_prestige_cooldown_secs)
+    }
+
+    pub fn prestige_lookahead(&self, upgrade_interval: Duration) -> Duration {
+        self.prestige_lookahead_secs
+            .map_or(upgrade_interval, Duration::from_secs)
+    }
+
+    pub fn click_delay(&self, task_type: TaskType) -> Duration {
+        self.get(task_type)
+            .click_delay_ms
+            .map_or(Timings::CLICK_DELAY, Duration::from_millis)
+    }
+
+    pub fn panel_open_delay(&self, task_type: TaskType) -> Duration {
+        self.get(task_type)
+            .panel_open_delay_ms
+            .map_or(Timings::PANEL_OPEN_DELAY, Duration::from_millis)
+    }
+
+    // Index of the upgrade row after which to pause, rounding up so 0.5 of 5 rows pauses after
+    // the third
+    pub fn upgrade_pause_index(&self, rows: usize) -> usize {
+        let fraction = self.upgrade_pause_at.clamp(0.0, 1.0);
+        ((rows as f64 * fraction).ceil() as usize).saturating_sub(1)
+    }
+
+    pub fn upgrade_backoff_max(&self) -> Option<Duration> {
+        self.upgrade_backoff_max_secs.map(Duration::from_secs)
+    }
+
+    pub fn priority(&self, task_type: TaskType) -> u8 {
+        self.get(task_type)
+            .priority
+            .unwrap_or_else(|| task_type.priority())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TaskConfig {
+    // Run as soon as the bot is activated instead of waiting a full interval
+    pub run_on_start: bool,
+    // Overrides the built-in interval for this task
+    pub interval_secs: Option<u64>,
+    // Overrides the built-in priority; higher runs first when several tasks are due at once
+    pub priority: Option<u8>,
+    // Overrides the pause around each of this task's clicks, in milliseconds
+    pub click_delay_ms: Option<u64>,
+    // Overrides the wait after opening this task's panel, in milliseconds
+    pub panel_open_delay_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+    // Entries kept in memory for scrolling and export
+    pub log_retention: usize,
+    // "json" or "csv" for the export hotkey
+    pub export_format: ExportFormat,
+    // Log every mining click with its coordinates at trace level, shown under the Trace filter
+    pub trace_clicks: bool,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            log_retention: UIConfig::LOG_RETENTION,
+            export_format: ExportFormat::Json,
+            trace_clicks: false,
+        }
+    }
+}
+
+// Appends every log line to a file, rotating it to `<path>.1` once it grows past `max_bytes`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogFileConfig {
+    pub enabled: bool,
+    pub path: String,
+    pub max_bytes: u64,
+    // Also write trace entries, which are left out by default
+    pub trace: bool,
+}
+
+impl Default for LogFileConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: "bot.log".to_string(),
+            max_bytes: 1024 * 1024,
+            trace: false,
+        }
+    }
+}
+
+// Live JSON status for external overlays
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatusFileConfig {
+    pub enabled: bool,
+    pub path: String,
+    pub interval_ms: u64,
+}
+
+impl StatusFileConfig {
+    pub fn interval(&self) -> Duration {
+        Duration::from_millis(self.interval_ms)
+    }
+}
+
+impl Default for StatusFileConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: "status.json".to_string(),
+            interval_ms: 1000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputHistoryConfig {
+    // Number of recent input actions kept for export; 0 disables recording
+    pub capacity: usize,
+}
+
+impl Default for InputHistoryConfig {
+    fn default() -> Self {
+        Self { capacity: 500 }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoDisableConfig {
+    // Disable a task after this many failed runs in a row; 0 never disables
+    pub max_consecutive_errors: u32,
+}
+
+impl Default for AutoDisableConfig {
+    fn default() -> Self {
+        Self {
+            max_consecutive_errors: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LimitsConfig {
+    // Pause once a session has run this many minutes; 0 is unlimited
+    pub max_runtime_mins: u64,
+    // Pause once a session has made this many mining clicks; 0 is unlimited
+    pub max_clicks: u64,
+}
+
+impl LimitsConfig {
+    pub fn max_runtime(&self) -> Option<Duration> {
+        (self.max_runtime_mins > 0).then(|| Duration::from_secs(self.max_runtime_mins * 60))
+    }
+
+    pub fn max_clicks(&self) -> Option<u64> {
+        (self.max_clicks > 0).then_some(self.max_clicks)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RandomizationConfig {
+    // Max pixel offset applied to each click in either axis; 0 clicks exact positions
+    pub jitter: i32,
+    // Randomizes each task interval by up to ±this percent per cycle; 0 keeps fixed intervals
+    pub interval_jitter_pct: f64,
+    // Fixed RNG seed for reproducible runs; random when unset
+    pub seed: Option<u64>,
+    // Run tasks that come due together in a random order instead of by priority; prestige
+    // still goes first
+    pub randomize_task_order: bool,
+}
+
+// Wheel notches scrolled down to reveal the lower rows; the same amount is scrolled back up after
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScrollConfig {
+    pub upgrades_scroll_amount: i32,
+    pub souls_scroll_amount: i32,
+    // Flip the wheel direction for systems with inverted (natural) scrolling
+    pub invert_scroll: bool,
+}
+
+impl ScrollConfig {
+    // Wheel notch sent for a scroll of `amount`; positive amounts scroll up
+    pub fn notch(&self, amount: i32) -> i32 {
+        let notch = if amount > 0 { -1 } else { 1 };
+        if self.invert_scroll {
+            -notch
+        } else {
+            notch
+        }
+    }
+}
+
+impl Default for ScrollConfig {
+    fn default() -> Self {
+        Self {
+            upgrades_scroll_amount: 8,
+            souls_scroll_amount: 2,
+            invert_scroll: false,
+        }
+    }
+}
+
+// Bell and desktop alerts (via `notify-send`) for the listed events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotifyConfig {
+    pub bell: bool,
+    pub desktop: bool,
+    pub events: Vec<NotifyEvent>,
+}
+
+impl Default for NotifyConfig {
+    fn default() -> Self {
+        Self {
+            bell: false,
+            desktop: false,
+            events: vec![
+                NotifyEvent::PrestigeComplete,
+                NotifyEvent::TaskDisabled,
+                NotifyEvent::GameFrozen,
+            ],
+        }
+    }
+}
+
+// Appends the CPM once a second to `<dir>/cpm_YYYYmmdd_HHMMSS.csv`, one file per session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CpmLogConfig {
+    pub enabled: bool,
+    pub dir: String,
+}
+
+impl Default for CpmLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dir: SESSIONS_DIR.to_string(),
+        }
+    }
+}
+
+// Holds off all clicks while the foreground window's title doesn't contain `title` (ignoring
+// case). Reads the title with `xdotool` on Linux; elsewhere it does nothing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FocusGuardConfig {
+    pub enabled: bool,
+    pub title: String,
+    pub interval_ms: u64,
+}
+
+impl FocusGuardConfig {
+    pub fn interval(&self) -> Duration {
+        Duration::from_millis(self.interval_ms)
+    }
+}
+
+impl Default for FocusGuardConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            title: "Idle Cave Miner".to_string(),
+            interval_ms: 500,
+        }
+    }
+}
+
+// Pauses the bot when running on battery below `min_percent`; resuming is left to the user.
+// Linux only; does nothing where no battery can be read.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BatteryConfig {
+    pub enabled: bool,
+    pub min_percent: u8,
+    pub interval_secs: u64,
+}
+
+impl BatteryConfig {
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs)
+    }
+}
+
+impl Default for BatteryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_percent: 20,
+            interval_secs: 60,
+        }
+    }
+}
+
+// Line-command control server, bound to 127.0.0.1 only
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ControlConfig {
+    pub enabled: bool,
+    pub port: u16,
+}
+
+impl Default for ControlConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 7878,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotifyEvent {
+    PrestigeComplete,
+    TaskDisabled,
+    CpmAlarm,
+    GameFrozen,
+    LimitReached,
+    BatteryLow,
+    InputUnavailable,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DisplayConfig {
+    // How often the UI redraws and samples CPM, between 16 and 1000 ms; raise it on slow terminals
+    pub tick_rate_ms: u64,
+    // Built-in color scheme: `default` or `high-contrast`
+    pub theme_preset: ThemePreset,
+    // Custom colors replacing the preset; unset roles keep the default look.
+    // Colors are names like `"light-blue"`, `"#ff8800"` or a 256-color index like `"42"`.
+    pub theme: Option<Theme>,
+    // Seconds after activation before CPM is shown; it swings wildly over a tiny window
+    pub cpm_warmup_secs: u64,
+}
+
+impl DisplayConfig {
+    pub fn tick_rate(&self) -> Duration {
+        Duration::from_millis(self.tick_rate_ms)
+    }
+
+    pub fn cpm_warmup(&self) -> Duration {
+        Duration::from_secs(self.cpm_warmup_secs)
+    }
+
+    pub fn theme(&self) -> Theme {
+        self.theme.clone().unwrap_or_else(|| self.theme_preset.theme())
+    }
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            tick_rate_ms: UIConfig::TICK_RATE.as_millis() as u64,
+            theme_preset: ThemePreset::Default,
+            theme: None,
+            cpm_warmup_secs: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThemePreset {
+    #[default]
+    Default,
+    // Terminal-default text and bright accents, readable on light and dark backgrounds
+    HighContrast,
+}
+
+impl ThemePreset {
+    pub fn theme(self) -> Theme {
+        match self {
+            ThemePreset::Default => Theme::default(),
+            ThemePreset::HighContrast => Theme {
+                header: Color::Reset,
+                active: Color::Green,
+                paused: Color::Red,
+                graph: Color::Blue,
+                footer: Color::Reset,
+                prompt: Color::Red,
+                disabled: Color::Gray,
+                upgrades: Color::Blue,
+                souls: Color::Magenta,
+                relics: Color::Cyan,
+                forge: Color::Red,
+                alchemy: Color::Green,
+                miner: Color::Yellow,
+                macro_task: Color::White,
+                prestige: Color::Yellow,
+                log_info: Color::Reset,
+                log_success: Color::Green,
+                log_warning: Color::Magenta,
+                log_error: Color::Red,
+                log_task: Color::Blue,
+                log_trace: Color::Gray,
+            },
+        }
+    }
+}
+
+// Colors for each part of the UI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    pub header: Color,
+    pub active: Color,
+    pub paused: Color,
+    pub graph: Color,
+    pub footer: Color,
+    // Quit confirmation and the mouse readout
+    pub prompt: Color,
+    // Gauges of disabled tasks
+    pub disabled: Color,
+    pub upgrades: Color,
+    pub souls: Color,
+    pub relics: Color,
+    pub forge: Color,
+    pub alchemy: Color,
+    pub miner: Color,
+    #[serde(rename = "macro")]
+    pub macro_task: Color,
+    pub prestige: Color,
+    pub log_info: Color,
+    pub log_success: Color,
+    pub log_warning: Color,
+    pub log_error: Color,
+    pub log_task: Color,
+    pub log_trace: Color,
+}
+
+impl Theme {
+    pub fn task(&self, task_type: TaskType) -> Color {
+        match task_type {
+            TaskType::Upgrades => self.upgrades,
+            TaskType::Souls => self.souls,
+            TaskType::Relics => self.relics,
+            TaskType::Forge => self.forge,
+            TaskType::Alchemy => self.alchemy,
+            TaskType::Miner => self.miner,
+            TaskType::Macro => self.macro_task,
+            TaskType::Prestige => self.prestige,
+        }
+    }
+
+    pub fn log(&self, level: LogLevel) -> Color {
+        match level {
+            LogLevel::Info => self.log_info,
+            LogLevel::Success => self.log_success,
+            LogLevel::Warning => self.log_warning,
+            LogLevel::Error => self.log_error,
+            LogLevel::Task => self.log_task,
+            LogLevel::Trace => self.log_trace,
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            header: Color::Cyan,
+            active: Color::Green,
+            paused: Color::Yellow,
+            graph: Color::Green,
+            footer: Color::DarkGray,
+            prompt: Color::Yellow,
+            disabled: Color::DarkGray,
+            upgrades: Color::Cyan,
+            souls: Color::Magenta,
+            relics: Color::LightBlue,
+            forge: Color::LightRed,
+            alchemy: Color::LightGreen,
+            miner: Color::LightYellow,
+            macro_task: Color::White,
+            prestige: Color::Yellow,
+            log_info: Color::Blue,
+            log_success: Color::Green,
+            log_warning: Color::Yellow,
+            log_error: Color::Red,
+            log_task: Color::Cyan,
+            log_trace: Color::DarkGray,
+        }
+    }
+}
+
+// Aids for finding coordinates while setting up the config
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TuningConfig {
+    // Show the live cursor position in the footer
+    pub show_mouse: bool,
+}
+
+// Waits for a button's color to appear before clicking it, instead of a fixed sleep.
+// Needs the `pixel-check` build feature; unset colors keep the fixed sleep. Prestige colors are
+// set per step in `positions.prestige_steps`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PixelCheckConfig {
+    pub enabled: bool,
+    // Largest per-channel difference still treated as a match
+    pub tolerance: u8,
+    pub timeout_ms: u64,
+    pub poll_ms: u64,
+    // Color of `positions.upgrade_affordable_probe` while an upgrade can be bought
+    pub upgrade_affordable: Option<Rgb>,
+    // Re-clicks of a verified click whose check pixel didn't change; 0 turns verification off
+    pub click_retries: u32,
+    // Wait after a verified click before re-checking its pixel
+    pub click_verify_ms: u64,
+}
+
+impl PixelCheckConfig {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms)
+    }
+
+    pub fn poll(&self) -> Duration {
+        Duration::from_millis(self.poll_ms.max(10))
+    }
+
+    pub fn click_verify(&self) -> Duration {
+        Duration::from_millis(self.click_verify_ms)
+    }
+}
+
+impl Default for PixelCheckConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            tolerance: 24,
+            timeout_ms: 3000,
+            poll_ms: 100,
+            upgrade_affordable: None,
+            click_retries: 2,
+            click_verify_ms: 300,
+        }
+    }
+}
+
+// Glides the cursor to task targets along a curve instead of jumping. Mining clicks always jump.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MovementConfig {
+    pub humanlike: bool,
+    pub steps: u32,
+    pub duration_ms: u64,
+}
+
+impl MovementConfig {
+    pub fn step_delay(&self) -> Duration {
+        Duration::from_millis(self.duration_ms / self.steps.max(1) as u64)
+    }
+}
+
+impl Default for MovementConfig {
+    fn default() -> Self {
+        Self {
+            humanlike: false,
+            steps: 12,
+            duration_ms: 120,
+        }
+    }
+}
+
+// Pauses the bot when the user moves the physical mouse
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MouseGuardConfig {
+    pub enabled: bool,
+    // Cursor travel between samples that counts as movement
+    pub threshold_px: i32,
+    // Distance from the bot's last commanded position still treated as a bot move
+    pub tolerance_px: i32,
+}
+
+impl Default for MouseGuardConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold_px: 30,
+            tolerance_px: 5,
+        }
+    }
+}
+
+// Screen rectangle, in actual screen pixels, that clicks must land in; anything outside is
+// refused. Meant to be set to the game window.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SafeAreaConfig {
+    pub enabled: bool,
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl SafeAreaConfig {
+    // Always true while the guard is off
+    pub fn allows(&self, pos: Position) -> bool {
+        !self.enabled
+            || ((self.x..self.x + self.width).contains(&pos.x)
+                && (self.y..self.y + self.height).contains(&pos.y))
+    }
+}
+
+impl Default for SafeAreaConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            x: 0,
+            y: 0,
+            width: 1920,
+            height: 1080,
+        }
+    }
+}
+
+// Pixel positions are authored against the reference resolution and scaled to the detected screen
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResolutionConfig {
+    pub scale: bool,
+    pub reference_width: i32,
+    pub reference_height: i32,
+}
+
+impl ResolutionConfig {
+    pub fn scale_factors(&self, screen: (i32, i32)) -> (f64, f64) {
+        if !self.scale {
+            return (1.0, 1.0);
+        }
+        (
+            screen.0 as f64 / self.reference_width as f64,
+            screen.1 as f64 / self.reference_height as f64,
+        )
+    }
+
+    // Inverse of `scale_position`: turns a screen position back into reference coordinates
+    pub fn unscale_position(&self, pos: Position, screen: (i32, i32)) -> Position {
+        let (sx, sy) = self.scale_factors(screen);
+        Position::new(
+            (pos.x as f64 / sx).round() as i32,
+            (pos.y as f64 / sy).round() as i32,
+        )
+    }
+
+    pub fn scale_position(&self, pos: Position, screen: (i32, i32)) -> Position {
+        let (sx, sy) = self.scale_factors(screen);
+        Position::new(
+            (pos.x as f64 * sx).round() as i32,
+            (pos.y as f64 * sy).round() as i32,
+        )
+    }
+}
+
+impl Default for ResolutionConfig {
+    fn default() -> Self {
+        Self {
+            scale: true,
+            reference_width: 1920,
+            reference_height: 1440,
+        }
+    }
+}
+
+// A set of positions, task timings and hotkeys that can be switched to at runtime.
+// Sections left out of a profile use the built-in defaults, not the top-level ones.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub name: String,
+    pub positions: PositionsConfig,
+    pub tasks: TasksConfig,
+    pub keybindings: Keybindings,
+}
+
+// All profiles available to the bot; the first is always built from the top-level config
+pub struct ProfileSet {
+    profiles: Vec<Arc<Profile>>,
+}
+
+impl ProfileSet {
+    pub fn from_config(config: &Config) -> Self {
+        let default = Profile {
+            name: "default".to_string(),
+            positions: config.positions.clone(),
+            tasks: config.tasks.clone(),
+            keybindings: config.keybindings.clone(),
+        };
+        let profiles = std::iter::once(default)
+            .chain(config.profiles.iter().cloned())
+            .map(Arc::new)
+            .collect();
+        Self { profiles }
+    }
+
+    pub fn get(&self, index: usize) -> Arc<Profile> {
+        self.profiles[index % self.profiles.len()].clone()
+    }
+
+    pub fn len(&self) -> usize {
+        self.profiles.len()
+    }
+}
+
+// Global hotkeys. Keys use device_query names such as "F1", "Key5" or "Home".
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Keybindings {
+    #[serde(with = "keycode_format")]
+    pub toggle: Keycode,
+    #[serde(with = "keycode_format")]
+    pub upgrades: Keycode,
+    #[serde(with = "keycode_format")]
+    pub souls: Keycode,
+    #[serde(with = "keycode_format")]
+    pub prestige: Keycode,
+    #[serde(with = "keycode_format")]
+    pub dump_input: Keycode,
+    #[serde(with = "keycode_format")]
+    pub relics: Keycode,
+    #[serde(with = "keycode_format")]
+    pub forge: Keycode,
+    #[serde(with = "keycode_format")]
+    pub alchemy: Keycode,
+    #[serde(with = "keycode_format")]
+    pub arm_prestige: Keycode,
+    #[serde(with = "keycode_format")]
+    pub next_profile: Keycode,
+    #[serde(with = "keycode_format")]
+    pub export_log: Keycode,
+    #[serde(with = "keycode_format")]
+    pub miner: Keycode,
+    #[serde(rename = "macro", with = "keycode_format")]
+    pub macro_task: Keycode,
+    // Pause or resume just mining, or just the scheduled tasks
+    #[serde(with = "keycode_format")]
+    pub toggle_mining: Keycode,
+    #[serde(with = "keycode_format")]
+    pub toggle_tasks: Keycode,
+    // Pauses and shuts the bot down from anywhere, even with the game focused
+    pub kill_switch: KeyCombo,
+    // Held with a task's key to run that task right away instead of toggling it; empty turns it
+    // off
+    pub run_now: KeyCombo,
+}
+
+impl Keybindings {
+    // In footer order
+    pub fn bindings(&self) -> [(Action, Keycode); 15] {
+        [
+            (Action::Toggle, self.toggle),
+            (Action::ToggleMining, self.toggle_mining),
+            (Action::ToggleTasks, self.toggle_tasks),
+            (Action::Upgrades, self.upgrades),
+            (Action::Souls, self.souls),
+            (Action::Prestige, self.prestige),
+            (Action::DumpInput, self.dump_input),
+            (Action::Relics, self.relics),
+            (Action::Forge, self.forge),
+            (Action::Alchemy, self.alchemy),
+            (Action::Miner, self.miner),
+            (Action::Macro, self.macro_task),
+            (Action::ArmPrestige, self.arm_prestige),
+            (Action::NextProfile, self.next_profile),
+            (Action::ExportLog, self.export_log),
+        ]
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.kill_switch.0.is_empty() {
+            bail!("kill_switch needs at least one key");
+        }
+
+        let bindings = self.bindings();
+        for (i, (action, key)) in bindings.iter().enumerate() {
+            if *key == Keycode::Escape {
+                bail!("{} can't be bound to Escape, it's reserved for exit", action.label());
+            }
+            if let Some((other, _)) = bindings[..i].iter().find(|(_, k)| k == key) {
+                bail!("{} is bound to both {} and {}", key, other.label(), action.label());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn action_for(&self, key: Keycode) -> Option<Action> {
+        self.bindings()
+            .into_iter()
+            .find(|(_, bound)| *bound == key)
+            .map(|(action, _)| action)
+    }
+}
+
+impl Default for Keybindings {
+    fn default() -> Self {
+        Self {
+            toggle: Keycode::F1,
+            upgrades: Keycode::F2,
+            souls: Keycode::F3,
+            prestige: Keycode::F4,
+            dump_input: Keycode::F5,
+            relics: Keycode::F6,
+            forge: Keycode::F7,
+            alchemy: Keycode::F10,
+            arm_prestige: Keycode::F8,
+            next_profile: Keycode::F9,
+            export_log: Keycode::F11,
+            miner: Keycode::F12,
+            // The function keys are all taken; these sit above the arrows on most keyboards and
+            // the terminal UI doesn't use them for scrolling
+            macro_task: Keycode::Insert,
+            toggle_mining: Keycode::Home,
+            toggle_tasks: Keycode::Delete,
+            kill_switch: KeyCombo(vec![Keycode::LControl, Keycode::LShift, Keycode::K]),
+            run_now: KeyCombo(vec![Keycode::LShift]),
+        }
+    }
+}
+
+// Keys held together, written as "LControl+LShift+K". Either side of a modifier counts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyCombo(pub Vec<Keycode>);
+
+impl KeyCombo {
+    pub fn is_held(&self, keys: &[Keycode]) -> bool {
+        !self.0.is_empty()
+            && self
+                .0
+                .iter()
+                .all(|&key| keys.iter().any(|&held| same_key(key, held)))
+    }
+}
+
+fn same_key(a: Keycode, b: Keycode) -> bool {
+    use Keycode::*;
+    a == b
+        || matches!(
+            (a, b),
+            (LControl | RControl, LControl | RControl)
+                | (LShift | RShift, LShift | RShift)
+                | (LAlt | RAlt, LAlt | RAlt)
+        )
+}
+
+impl fmt::Display for KeyCombo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<String> = self.0.iter().map(|key| key.to_string()).collect();
+        f.write_str(&names.join("+"))
+    }
+}
+
+impl FromStr for KeyCombo {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        s.split('+')
+            .map(|name| name.trim().parse().map_err(|_| format!("unknown key \"{}\"", name.trim())))
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map(KeyCombo)
+    }
+}
+
+impl Serialize for KeyCombo {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyCombo {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+// `Keycode` has no serde support, so it goes through its string form
+mod keycode_format {
+    use device_query::Keycode;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(key: &Keycode, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(key)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Keycode, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse()
+            .map_err(|_| de::Error::custom(format!("unknown key \"{}\"", name)))
+    }
 }
 
 pub struct UpgradePositions;
 
 impl UpgradePositions {
+    // Default rows; override with `upgrade_rows` and `upgrade_rows_after_scroll` in the config
     // Positions before scrolling - first 5 upgrade rows
     pub const BEFORE_SCROLL: [Position; 5] = [
         Position::new(830, 300),
@@ -42,6 +1757,7 @@ impl UpgradePositions {
 pub struct SoulsPositions;
 
 impl SoulsPositions {
+    // Default rows; override with `souls_rows` and `souls_rows_after_scroll` in the config
     // First 6 soul upgrade rows before scrolling
     pub const BEFORE_SCROLL: [Position; 6] = [
         Position::new(830, 200),
@@ -56,23 +1772,194 @@ impl SoulsPositions {
     pub const AFTER_SCROLL: Position = Position::new(830, 1050);
 }
 
+pub struct AlchemyPositions;
+
+impl AlchemyPositions {
+    // Alchemy rows clicked by default; override with `alchemy_rows` in the config
+    pub const ROWS: [Position; 3] = [
+        Position::new(1280, 500),
+        Position::new(1280, 700),
+        Position::new(1280, 900),
+    ];
+}
+
+pub struct RelicPositions;
+
+impl RelicPositions {
+    // Relic rows clicked by default; override with `relic_rows` in the config
+    pub const ROWS: [Position; 5] = [
+        Position::new(830, 300),
+        Position::new(830, 470),
+        Position::new(830, 640),
+        Position::new(830, 800),
+        Position::new(830, 960),
+    ];
+}
+
 pub struct Timings;
 
 impl Timings {
     pub const MINING_DELAY: Duration = Duration::from_millis(50);
+    // Mining delay right after activation when `mining.ramp_ms` is set
+    pub const MINING_RAMP_START: Duration = Duration::from_millis(500);
     pub const CLICK_DELAY: Duration = Duration::from_millis(50);
+    // Time for a panel's opening animation before its rows are clicked
+    pub const PANEL_OPEN_DELAY: Duration = Duration::from_millis(150);
+    // How long `--verify-positions` rests on each target
+    pub const VERIFY_DWELL: Duration = Duration::from_secs(1);
     pub const SCROLL_DELAY: Duration = Duration::from_millis(50);
     pub const POST_SCROLL_DELAY: Duration = Duration::from_millis(100);
     pub const UPGRADE_INTERVAL: Duration = Duration::from_secs(30);
     pub const SOULS_INTERVAL: Duration = Duration::from_secs(600);
+    pub const RELICS_INTERVAL: Duration = Duration::from_secs(600);
+    pub const FORGE_INTERVAL: Duration = Duration::from_secs(300);
+    pub const ALCHEMY_INTERVAL: Duration = Duration::from_secs(900);
+    pub const MINER_INTERVAL: Duration = Duration::from_secs(300);
+    pub const MACRO_INTERVAL: Duration = Duration::from_secs(600);
     pub const PRESTIGE_INTERVAL: Duration = Duration::from_secs(600);
     pub const PRESTIGE_WAIT: Duration = Duration::from_secs(1);
     pub const PRESTIGE_COMPLETE_WAIT: Duration = Duration::from_secs(3);
+    // How long a first press to enable prestige waits for the confirming second press
+    pub const PRESTIGE_ENABLE_WINDOW: Duration = Duration::from_secs(2);
+    pub const CPM_SAMPLE_WINDOW: Duration = Duration::from_secs(10);
+    // How often the paused bot loop checks whether it has been activated
+    pub const IDLE_POLL: Duration = Duration::from_millis(250);
 }
 
 pub struct UIConfig;
 
 impl UIConfig {
-    pub const MAX_LOGS: usize = 50;
+    // Default number of log entries kept in memory; the log pane draws whatever fits
+    pub const LOG_RETENTION: usize = 1000;
     pub const TICK_RATE: Duration = Duration::from_millis(100);
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn migrates_prestige_positions_into_steps() {
+        let mut table: Table = toml::from_str(
+            "[positions]\n\
+             prestige_claim = { x = 10, y = 20 }\n\
+             [pixel_check]\n\
+             prestige_confirm = [1, 2, 3]\n",
+        )
+        .unwrap();
+        assert_eq!(migrate(&mut table).unwrap(), Some(1));
+
+        let config: Config = Value::Table(table).try_into().unwrap();
+        let steps = &config.positions.prestige_steps;
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(steps.len(), 3);
+        assert_eq!(steps[0], PositionsConfig::default().prestige_steps[0]);
+        assert_eq!(steps[1].at, CoordMode::from(Position::new(10, 20)));
+        assert_eq!(steps[2].ready, Some(Rgb(1, 2, 3)));
+    }
+
+    #[test]
+    fn rectangle_targets_click_inside_their_bounds() {
+        let positions: PositionsConfig = toml::from_str(
+            "mining = { x = 100, y = 200, width = 40, height = 20 }\n\
+             forge_icon = { x = 10, y = 20 }\n",
+        )
+        .unwrap();
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..100 {
+            let pos = positions.mining.pick_within(&mut rng).resolve(None).unwrap();
+            assert!((100..140).contains(&pos.x) && (200..220).contains(&pos.y));
+        }
+        assert_eq!(positions.forge_icon, CoordMode::from(Position::new(10, 20)));
+        assert_eq!(positions.forge_icon.pick_within(&mut rng), positions.forge_icon);
+
+        let negative: PositionsConfig =
+            toml::from_str("mining = { x = 1, y = 1, width = -5 }").unwrap();
+        assert!(negative.validate().is_err());
+        let overrun: PositionsConfig =
+            toml::from_str("mining = { xp = 0.9, yp = 0.5, wp = 0.2 }").unwrap();
+        assert!(overrun.validate().is_err());
+    }
+
+    #[test]
+    fn runs_without_clicks_or_time_look_ineffective() {
+        let retry = TaskRetryConfig::default();
+        assert!(retry.looks_ineffective(Duration::from_secs(5), 0));
+        assert!(retry.looks_ineffective(Duration::from_millis(100), 12));
+        assert!(!retry.looks_ineffective(Duration::from_millis(250), 1));
+    }
+
+    #[test]
+    fn retries_stop_at_the_cap_and_skip_prestige() {
+        let retry = TaskRetryConfig {
+            enabled: true,
+            max_retries: 2,
+            ..TaskRetryConfig::default()
+        };
+        let quick = Duration::from_millis(10);
+        assert!(retry.should_retry(TaskType::Upgrades, 0, quick, 0));
+        assert!(retry.should_retry(TaskType::Upgrades, 1, quick, 0));
+        assert!(!retry.should_retry(TaskType::Upgrades, 2, quick, 0));
+        assert!(!retry.should_retry(TaskType::Upgrades, 0, Duration::from_secs(1), 5));
+        assert!(!retry.should_retry(TaskType::Prestige, 0, quick, 0));
+
+        let disabled = TaskRetryConfig::default();
+        assert!(!disabled.should_retry(TaskType::Upgrades, 0, quick, 0));
+    }
+
+    #[test]
+    fn default_keybindings_are_valid() {
+        let keybindings = Keybindings::default();
+        keybindings.validate().unwrap();
+        // Most keyboards stop at F12
+        let beyond_f12 = [Keycode::F13, Keycode::F14, Keycode::F15, Keycode::F16];
+        for (action, key) in keybindings.bindings() {
+            assert!(!beyond_f12.contains(&key), "{} defaults to {}", action.label(), key);
+        }
+    }
+
+    #[test]
+    fn mining_ramp_eases_to_configured_delay() {
+        let mining = MiningConfig {
+            cps: 10,
+            ramp_ms: 2000,
+            ..MiningConfig::default()
+        };
+        assert_eq!(mining.ramped_delay(Duration::ZERO), Timings::MINING_RAMP_START);
+        assert_eq!(mining.ramped_delay(Duration::from_secs(1)), Duration::from_millis(300));
+        assert_eq!(mining.ramped_delay(Duration::from_secs(5)), mining.delay());
+
+        let unramped = MiningConfig::default();
+        assert_eq!(unramped.ramped_delay(Duration::ZERO), unramped.delay());
+    }
+
+    #[test]
+    fn scroll_back_returns_to_origin_either_way() {
+        for invert_scroll in [false, true] {
+            let scroll = ScrollConfig {
+                invert_scroll,
+                ..ScrollConfig::default()
+            };
+            let amount = scroll.upgrades_scroll_amount;
+            let down = scroll.notch(-amount) * amount;
+            let back = scroll.notch(amount) * amount;
+            assert_ne!(down, 0);
+            assert_eq!(down + back, 0);
+        }
+        let inverted = ScrollConfig {
+            invert_scroll: true,
+            ..ScrollConfig::default()
+        };
+        assert_eq!(inverted.notch(-1), -ScrollConfig::default().notch(-1));
+    }
+
+    #[test]
+    fn refuses_newer_versions() {
+        let mut table: Table = toml::from_str(&format!("version = {}", CONFIG_VERSION + 1)).unwrap();
+        assert!(migrate(&mut table).is_err());
+
+        let mut current: Table = toml::from_str(&format!("version = {}", CONFIG_VERSION)).unwrap();
+        assert_eq!(migrate(&mut current).unwrap(), None);
+    }
+}
@@ -0,0 +1,186 @@
+use crate::{
+    bot::Bot,
+    clock::Clock,
+    config::{ControlConfig, Timings},
+    logger::LogLevel,
+    types::TaskType,
+};
+use serde_json::json;
+use std::{net::Ipv4Addr, sync::Arc};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+
+// Line-based remote control on a loopback TCP port. Each command gets one line of JSON back:
+// the bot snapshot, or `{"error": ...}`.
+//
+//   status | toggle | pause | resume | enable <task> | disable <task> | run <task>
+pub struct ControlServer {
+    bot: Arc<Bot>,
+    config: ControlConfig,
+}
+
+impl ControlServer {
+    pub fn new(bot: Arc<Bot>, config: ControlConfig) -> Self {
+        Self { bot, config }
+    }
+
+    pub async fn run(&self) {
+        // Loopback only; reach it from another machine through an SSH tunnel
+        let listener = match TcpListener::bind((Ipv4Addr::LOCALHOST, self.config.port)).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                self.bot.get_logger().log(
+                    LogLevel::Error,
+                    &format!("Control server failed to bind port {}: {}", self.config.port, e),
+                );
+                return;
+            }
+        };
+        self.bot.get_logger().log(
+            LogLevel::Info,
+            &format!("Control server listening on 127.0.0.1:{}", self.config.port),
+        );
+
+        while !self.bot.is_shutting_down() {
+            let accepted = tokio::select! {
+                accepted = listener.accept() => accepted,
+                _ = tokio::time::sleep(Timings::IDLE_POLL) => continue,
+            };
+            match accepted {
+                Ok((stream, _)) => {
+                    let bot = self.bot.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = serve(stream, &bot).await {
+                            bot.get_logger()
+                                .log(LogLevel::Warning, &format!("Control connection failed: {}", e));
+                        }
+                    });
+                }
+                Err(e) => self
+                    .bot
+                    .get_logger()
+                    .log(LogLevel::Warning, &format!("Control server accept failed: {}", e)),
+            }
+        }
+    }
+}
+
+async fn serve(stream: TcpStream, bot: &Bot) -> std::io::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Some(line) = lines.next_line().await? {
+        if bot.is_shutting_down() {
+            break;
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reply = match handle_command(bot, &line) {
+            Ok(()) => serde_json::to_value(bot.snapshot()).unwrap_or_default(),
+            Err(error) => json!({ "error": error }),
+        };
+        writer.write_all(format!("{}\n", reply).as_bytes()).await?;
+    }
+    Ok(())
+}
+
+fn handle_command<C: Clock>(bot: &Bot<C>, line: &str) -> Result<(), String> {
+    let mut words = line.split_whitespace();
+    let command = words.next().unwrap_or_default().to_lowercase();
+    let task = words.next().map(|name| {
+        TaskType::from_name(name).ok_or_else(|| format!("unknown task \"{}\"", name))
+    });
+    if words.next().is_some() {
+        return Err(format!("unexpected arguments in \"{}\"", line.trim()));
+    }
+
+    match (command.as_str(), task) {
+        ("status", None) => {}
+        ("toggle", None) => bot.toggle(),
+        ("pause", None) if bot.is_active() => bot.toggle(),
+        ("resume", None) if !bot.is_active() => bot.toggle(),
+        ("pause" | "resume", None) => {}
+        ("enable", Some(task)) => bot.set_task_enabled(task?, true),
+        ("disable", Some(task)) => bot.set_task_enabled(task?, false),
+        ("run", Some(task)) => bot.run_now(task?),
+        _ => return Err(format!("unknown command \"{}\"", line.trim())),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{clock::FakeClock, config::Config};
+
+    fn last_log(bot: &Bot<FakeClock>) -> String {
+        bot.get_logger().get_entries().last().map(|entry| entry.message.clone()).unwrap_or_default()
+    }
+
+    #[test]
+    fn status_changes_nothing() {
+        let bot = Bot::for_test(Config::default(), &FakeClock::new());
+        assert_eq!(handle_command(&bot, "status"), Ok(()));
+        assert_eq!(handle_command(&bot, "  STATUS  "), Ok(()));
+        assert!(!bot.is_active());
+    }
+
+    #[test]
+    fn pause_and_resume_only_act_when_they_change_the_state() {
+        let bot = Bot::for_test(Config::default(), &FakeClock::new());
+        assert_eq!(handle_command(&bot, "pause"), Ok(()));
+        assert!(!bot.is_active());
+
+        assert_eq!(handle_command(&bot, "resume"), Ok(()));
+        assert!(bot.is_active());
+        assert_eq!(handle_command(&bot, "resume"), Ok(()));
+        assert!(bot.is_active());
+
+        assert_eq!(handle_command(&bot, "pause"), Ok(()));
+        assert!(!bot.is_active());
+        assert_eq!(handle_command(&bot, "toggle"), Ok(()));
+        assert!(bot.is_active());
+    }
+
+    #[test]
+    fn task_commands_take_a_known_task() {
+        let bot = Bot::for_test(Config::default(), &FakeClock::new());
+        assert_eq!(handle_command(&bot, "disable souls"), Ok(()));
+        assert!(!bot.is_task_enabled(TaskType::Souls));
+        assert_eq!(handle_command(&bot, "enable souls"), Ok(()));
+        assert!(bot.is_task_enabled(TaskType::Souls));
+        assert_eq!(handle_command(&bot, "run forge"), Ok(()));
+        assert_eq!(last_log(&bot), "Forge queued to run now");
+
+        for command in ["enable", "disable", "run"] {
+            let line = format!("{} gold", command);
+            assert_eq!(handle_command(&bot, &line), Err("unknown task \"gold\"".to_string()));
+            let line = command.to_string();
+            assert_eq!(handle_command(&bot, &line), Err(format!("unknown command \"{}\"", line)));
+        }
+        assert_eq!(last_log(&bot), "Forge queued to run now");
+        assert!(bot.is_task_enabled(TaskType::Souls));
+    }
+
+    #[test]
+    fn extra_arguments_are_rejected() {
+        let bot = Bot::for_test(Config::default(), &FakeClock::new());
+        assert_eq!(
+            handle_command(&bot, "disable souls now"),
+            Err("unexpected arguments in \"disable souls now\"".to_string())
+        );
+        assert!(bot.is_task_enabled(TaskType::Souls));
+        assert_eq!(
+            handle_command(&bot, "status souls"),
+            Err("unknown command \"status souls\"".to_string())
+        );
+        assert_eq!(
+            handle_command(&bot, "pause now"),
+            Err("unknown command \"pause now\"".to_string())
+        );
+        assert!(!bot.is_active());
+    }
+}
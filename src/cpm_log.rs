@@ -0,0 +1,84 @@
+use crate::{
+    clock::{Clock, SystemClock},
+    config::CpmLogConfig,
+    logger::{LogLevel, Logger},
+};
+use anyhow::{Context, Result};
+use chrono::Local;
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+const FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+
+// Writes one `timestamp,cpm` row per CPM sample for plotting a session afterwards
+pub struct CpmRecorder<C: Clock = SystemClock> {
+    clock: C,
+    writer: Option<BufWriter<File>>,
+    last_flush: Instant,
+}
+
+impl CpmRecorder {
+    pub fn new(config: &CpmLogConfig, logger: &Logger) -> Self {
+        Self::with_clock(config, logger, SystemClock)
+    }
+}
+
+impl<C: Clock> CpmRecorder<C> {
+    pub fn with_clock(config: &CpmLogConfig, logger: &Logger, clock: C) -> Self {
+        let writer = if config.enabled {
+            match open(Path::new(&config.dir)) {
+                Ok(writer) => Some(writer),
+                Err(e) => {
+                    logger.log(LogLevel::Warning, &format!("CPM log disabled: {:#}", e));
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        Self {
+            writer,
+            last_flush: clock.now(),
+            clock,
+        }
+    }
+
+    pub fn record(&mut self, cpm: u64, logger: &Logger) {
+        let Some(writer) = &mut self.writer else {
+            return;
+        };
+        let mut result = writeln!(writer, "{},{}", Local::now().to_rfc3339(), cpm);
+        if result.is_ok() && self.clock.elapsed(self.last_flush) >= FLUSH_INTERVAL {
+            self.last_flush = self.clock.now();
+            result = writer.flush();
+        }
+
+        // Stop on the first failure rather than logging a warning every second
+        if let Err(e) = result {
+            logger.log(LogLevel::Warning, &format!("CPM log stopped: {}", e));
+            self.writer = None;
+        }
+    }
+}
+
+impl<C: Clock> Drop for CpmRecorder<C> {
+    fn drop(&mut self) {
+        if let Some(writer) = &mut self.writer {
+            let _ = writer.flush();
+        }
+    }
+}
+
+fn open(dir: &Path) -> Result<BufWriter<File>> {
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let path = dir.join(format!("cpm_{}.csv", Local::now().format("%Y%m%d_%H%M%S")));
+    let file = File::create(&path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut writer = BufWriter::new(file);
+    writeln!(writer, "timestamp,cpm")?;
+    Ok(writer)
+}
@@ -0,0 +1,83 @@
+use crate::{
+    clock::{Clock, SystemClock},
+    config::FocusGuardConfig,
+};
+use std::{io, time::Instant};
+
+pub enum FocusEvent {
+    // Another window came to the front; carries its title
+    Lost(String),
+    Regained,
+    // The active window can't be read here, so the guard stays out of the way
+    Unavailable,
+}
+
+// Tracks whether the game window is in front, re-reading the active window title at most once
+// per interval
+pub struct WindowFocus<C: Clock = SystemClock> {
+    clock: C,
+    config: FocusGuardConfig,
+    last_check: Option<Instant>,
+    focused: bool,
+    unavailable: bool,
+}
+
+impl<C: Clock> WindowFocus<C> {
+    pub fn with_clock(config: FocusGuardConfig, clock: C) -> Self {
+        Self {
+            clock,
+            config,
+            last_check: None,
+            focused: true,
+            unavailable: false,
+        }
+    }
+
+    pub fn is_focused(&self) -> bool {
+        !self.config.enabled || self.focused
+    }
+
+    pub fn check(&mut self) -> Option<FocusEvent> {
+        if !self.config.enabled || self.unavailable {
+            return None;
+        }
+        if self.last_check.is_some_and(|at| self.clock.elapsed(at) < self.config.interval()) {
+            return None;
+        }
+        self.last_check = Some(self.clock.now());
+
+        let Ok(title) = active_window_title() else {
+            self.unavailable = true;
+            self.focused = true;
+            return Some(FocusEvent::Unavailable);
+        };
+        let focused = title.to_lowercase().contains(&self.config.title.to_lowercase());
+        if focused == self.focused {
+            return None;
+        }
+        self.focused = focused;
+        Some(if focused { FocusEvent::Regained } else { FocusEvent::Lost(title) })
+    }
+}
+
+// Asks `xdotool` for the active window's title. An error means it can't be asked at all; an
+// empty title means no window is focused.
+#[cfg(target_os = "linux")]
+fn active_window_title() -> io::Result<String> {
+    use std::process::{Command, Stdio};
+
+    let output = Command::new("xdotool")
+        .args(["getactivewindow", "getwindowname"])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()?;
+    if !output.status.success() {
+        return Ok(String::new());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn active_window_title() -> io::Result<String> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "window detection isn't implemented here"))
+}
@@ -0,0 +1,61 @@
+use crate::{
+    clock::{Clock, SystemClock},
+    config::HealthCheckConfig,
+    pixel,
+    types::Position,
+};
+use std::time::Instant;
+
+pub enum HealthEvent {
+    // The watched region hasn't changed for this many checks in a row
+    Frozen(u32),
+}
+
+pub struct HealthMonitor<C: Clock = SystemClock> {
+    clock: C,
+    config: HealthCheckConfig,
+    last_check: Instant,
+    last_hash: Option<u64>,
+    unchanged: u32,
+}
+
+impl<C: Clock> HealthMonitor<C> {
+    pub fn with_clock(config: HealthCheckConfig, clock: C) -> Self {
+        Self {
+            last_check: clock.now(),
+            clock,
+            config,
+            last_hash: None,
+            unchanged: 0,
+        }
+    }
+
+    // Does nothing in builds that can't capture the screen
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled && pixel::is_supported()
+    }
+
+    // Captures the region once per interval and reports when it has stopped changing
+    pub fn check(&mut self, origin: Position) -> Option<HealthEvent> {
+        if !self.is_enabled() || self.clock.elapsed(self.last_check) < self.config.interval() {
+            return None;
+        }
+        self.last_check = self.clock.now();
+
+        // A failed capture says nothing about the game, so it neither counts nor resets
+        let hash = pixel::region_hash(origin, self.config.width, self.config.height).ok()?;
+        if self.last_hash.replace(hash) != Some(hash) {
+            self.unchanged = 0;
+            return None;
+        }
+
+        self.unchanged += 1;
+        (self.unchanged >= self.config.max_unchanged).then_some(HealthEvent::Frozen(self.unchanged))
+    }
+
+    pub fn reset(&mut self) {
+        self.last_check = self.clock.now();
+        self.last_hash = None;
+        self.unchanged = 0;
+    }
+}
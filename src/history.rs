@@ -0,0 +1,106 @@
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use parking_lot::Mutex;
+use std::{collections::VecDeque, fmt::Write, fs, path::Path};
+
+pub struct InputAction {
+    pub timestamp: DateTime<Local>,
+    pub description: String,
+}
+
+// Ring buffer of the most recent raw input actions, for debugging a bad session
+pub struct InputHistory {
+    actions: Mutex<VecDeque<InputAction>>,
+    capacity: usize,
+}
+
+impl InputHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            actions: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity,
+        }
+    }
+
+    pub fn record(&self, description: String) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut actions = self.actions.lock();
+        if actions.len() >= self.capacity {
+            actions.pop_front();
+        }
+        actions.push_back(InputAction {
+            timestamp: Local::now(),
+            description,
+        });
+    }
+
+    #[cfg(test)]
+    pub fn descriptions(&self) -> Vec<String> {
+        self.actions.lock().iter().map(|action| action.description.clone()).collect()
+    }
+
+    pub fn format(&self) -> String {
+        let actions = self.actions.lock();
+        let mut out = String::new();
+        for action in actions.iter() {
+            let _ = writeln!(
+                out,
+                "{} {}",
+                action.timestamp.format("%Y-%m-%d %H:%M:%S%.3f"),
+                action.description
+            );
+        }
+        out
+    }
+
+    // Writes the buffer to `path`, returning the number of actions exported
+    pub fn export(&self, path: impl AsRef<Path>) -> Result<usize> {
+        let count = self.actions.lock().len();
+        fs::write(path, self.format())?;
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_only_the_most_recent_actions() {
+        let history = InputHistory::new(3);
+        for i in 0..5 {
+            history.record(format!("move ({}, 0)", i));
+        }
+        assert_eq!(history.descriptions(), ["move (2, 0)", "move (3, 0)", "move (4, 0)"]);
+
+        let disabled = InputHistory::new(0);
+        disabled.record("left click".to_string());
+        assert!(disabled.descriptions().is_empty());
+    }
+
+    #[test]
+    fn dumps_one_timestamped_line_per_action() {
+        let history = InputHistory::new(10);
+        history.record("move (10, 20)".to_string());
+        history.record("left click".to_string());
+
+        let path = std::env::temp_dir().join(format!("history_test_{}.txt", std::process::id()));
+        assert_eq!(history.export(&path).unwrap(), 2);
+        let dump = fs::read_to_string(&path).unwrap();
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(dump, history.format());
+        let lines: Vec<&str> = dump.lines().collect();
+        assert_eq!(lines.len(), 2);
+        for (line, description) in lines.iter().zip(["move (10, 20)", "left click"]) {
+            // `2024-01-31 12:34:56.789 <description>`
+            let (timestamp, rest) = line.split_at(23);
+            let parsed = chrono::NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%d %H:%M:%S%.3f");
+            assert!(parsed.is_ok(), "{}", line);
+            assert_eq!(rest, format!(" {}", description));
+        }
+    }
+}
@@ -1,6 +1,19 @@
-use crate::bot::Bot;
+use crate::{
+    bot::Bot,
+    clock::{Clock, SystemClock},
+    types::Position,
+};
+use crossterm::event::{KeyCode, KeyModifiers};
 use device_query::{DeviceQuery, DeviceState, Keycode};
-use std::{sync::Arc, time::Duration};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+// device_query has no blocking key API, so polling backs off while the keyboard is idle
+const POLL_FAST: Duration = Duration::from_millis(50);
+const POLL_IDLE: Duration = Duration::from_millis(200);
+const IDLE_AFTER: Duration = Duration::from_secs(3);
 
 pub struct InputHandler {
     bot: Arc<Bot>,
@@ -16,17 +29,59 @@ impl InputHandler {
     }
 
     pub async fn run(&self) {
-        let mut key_states = KeyStates::default();
+        let mut key_states = vec![false; self.bot.profile().keybindings.bindings().len()];
+        let mut last_mouse = None;
+        let mut poll = PollBackoff::new();
+
+        while !self.bot.is_shutting_down() {
+            self.check_mouse_guard(&mut last_mouse);
 
-        loop {
+            // Re-read each poll so a profile switch takes its hotkeys with it
+            let profile = self.bot.profile();
+            let bindings = profile.keybindings.bindings();
             let keys = self.device.get_keys();
-            
-            self.handle_key(&keys, Keycode::F1, &mut key_states.f1, || self.bot.toggle());
-            self.handle_key(&keys, Keycode::F2, &mut key_states.f2, || self.bot.toggle_upgrades());
-            self.handle_key(&keys, Keycode::F3, &mut key_states.f3, || self.bot.toggle_souls());
-            self.handle_key(&keys, Keycode::F4, &mut key_states.f4, || self.bot.toggle_prestige());
-            
-            tokio::time::sleep(Duration::from_millis(50)).await;
+
+            if profile.keybindings.kill_switch.is_held(&keys) {
+                self.bot.kill_switch();
+                break;
+            }
+
+            let run_now = profile.keybindings.run_now.is_held(&keys);
+            for ((action, key), state) in bindings.iter().zip(key_states.iter_mut()) {
+                self.handle_key(&keys, *key, state, || {
+                    if run_now {
+                        self.bot.perform_action_now(*action);
+                    } else {
+                        self.bot.perform_action(*action);
+                    }
+                });
+            }
+
+            // The mouse guard needs fast samples to tell a drag from a bot move
+            let guarding = self.bot.is_active() && self.bot.get_config().mouse_guard.enabled;
+            tokio::time::sleep(poll.next(!keys.is_empty() || guarding)).await;
+        }
+    }
+
+    fn check_mouse_guard(&self, last_mouse: &mut Option<Position>) {
+        let guard = &self.bot.get_config().mouse_guard;
+        if !guard.enabled {
+            return;
+        }
+
+        let current = Position::from(self.device.get_mouse().coords);
+        let previous = last_mouse.replace(current);
+
+        let Some(previous) = previous else {
+            return;
+        };
+        if !self.bot.is_active() || distance(previous, current) <= guard.threshold_px {
+            return;
+        }
+
+        // Movement that didn't end where the bot sent the cursor came from the user
+        if distance(current, self.bot.last_commanded_position()) > guard.tolerance_px {
+            self.bot.pause_for_user_input();
         }
     }
 
@@ -42,10 +97,120 @@ impl InputHandler {
     }
 }
 
-#[derive(Default)]
-struct KeyStates {
-    f1: bool,
-    f2: bool,
-    f3: bool,
-    f4: bool,
-}
\ No newline at end of file
+// Polls quickly while keys are in use and doubles the interval up to `POLL_IDLE` once the
+// keyboard has been quiet for `IDLE_AFTER`. A tap shorter than the idle interval can be missed,
+// so the first press after a long idle spell may need holding a moment.
+struct PollBackoff<C: Clock = SystemClock> {
+    clock: C,
+    interval: Duration,
+    last_activity: Instant,
+}
+
+impl PollBackoff {
+    fn new() -> Self {
+        Self::with_clock(SystemClock)
+    }
+}
+
+impl<C: Clock> PollBackoff<C> {
+    fn with_clock(clock: C) -> Self {
+        Self {
+            interval: POLL_FAST,
+            last_activity: clock.now(),
+            clock,
+        }
+    }
+
+    fn next(&mut self, active: bool) -> Duration {
+        if active {
+            self.last_activity = self.clock.now();
+            self.interval = POLL_FAST;
+        } else if self.clock.elapsed(self.last_activity) >= IDLE_AFTER {
+            self.interval = (self.interval * 2).min(POLL_IDLE);
+        }
+        self.interval
+    }
+}
+
+// Maps a terminal key event onto the device_query key it corresponds to
+pub fn keycode_from_terminal(code: KeyCode) -> Option<Keycode> {
+    match code {
+        KeyCode::F(n) => format!("F{}", n).parse().ok(),
+        KeyCode::Char(c) if c.is_ascii_digit() => format!("Key{}", c).parse().ok(),
+        KeyCode::Char(' ') => Some(Keycode::Space),
+        KeyCode::Char(c) if c.is_ascii_alphabetic() => c.to_ascii_uppercase().to_string().parse().ok(),
+        KeyCode::Enter => Some(Keycode::Enter),
+        KeyCode::Tab => Some(Keycode::Tab),
+        KeyCode::Backspace => Some(Keycode::Backspace),
+        KeyCode::Insert => Some(Keycode::Insert),
+        KeyCode::Delete => Some(Keycode::Delete),
+        KeyCode::Home => Some(Keycode::Home),
+        KeyCode::End => Some(Keycode::End),
+        KeyCode::PageUp => Some(Keycode::PageUp),
+        KeyCode::PageDown => Some(Keycode::PageDown),
+        KeyCode::Up => Some(Keycode::Up),
+        KeyCode::Down => Some(Keycode::Down),
+        KeyCode::Left => Some(Keycode::Left),
+        KeyCode::Right => Some(Keycode::Right),
+        _ => None,
+    }
+}
+
+// The device_query modifier keys held in a terminal key event
+pub fn modifiers_from_terminal(modifiers: KeyModifiers) -> Vec<Keycode> {
+    [
+        (KeyModifiers::SHIFT, Keycode::LShift),
+        (KeyModifiers::CONTROL, Keycode::LControl),
+        (KeyModifiers::ALT, Keycode::LAlt),
+    ]
+    .into_iter()
+    .filter(|(modifier, _)| modifiers.contains(*modifier))
+    .map(|(_, key)| key)
+    .collect()
+}
+
+fn distance(a: Position, b: Position) -> i32 {
+    let delta = a - b;
+    delta.x.abs().max(delta.y.abs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FakeClock;
+
+    #[test]
+    fn poll_backs_off_when_idle_and_snaps_back_on_activity() {
+        let clock = FakeClock::new();
+        let mut poll = PollBackoff::with_clock(clock.clone());
+
+        clock.advance(IDLE_AFTER - Duration::from_millis(1));
+        assert_eq!(poll.next(false), POLL_FAST);
+        clock.advance(Duration::from_millis(1));
+        assert_eq!(poll.next(false), POLL_FAST * 2);
+        assert_eq!(poll.next(false), POLL_IDLE);
+        assert_eq!(poll.next(false), POLL_IDLE);
+
+        assert_eq!(poll.next(true), POLL_FAST);
+        // The idle period starts over from the last activity
+        clock.advance(IDLE_AFTER / 2);
+        assert_eq!(poll.next(false), POLL_FAST);
+    }
+
+    // Wake-ups over an idle minute: a fixed fast poll would take 1200, the backoff about 350,
+    // which is where the idle CPU saving comes from
+    #[test]
+    fn idle_minute_polls_far_less_often() {
+        let clock = FakeClock::new();
+        let mut poll = PollBackoff::with_clock(clock.clone());
+        let start = clock.now();
+        let mut wakeups = 0;
+        while clock.elapsed(start) < Duration::from_secs(60) {
+            clock.advance(poll.next(false));
+            wakeups += 1;
+        }
+        let fixed = Duration::from_secs(60).as_millis() / POLL_FAST.as_millis();
+        assert_eq!(fixed, 1200);
+        assert!(wakeups * 3 < fixed, "{} wake-ups", wakeups);
+    }
+}
@@ -1,25 +1,38 @@
-use chrono::{DateTime, Local};
-use parking_lot::RwLock;
-use ratatui::style::Color;
-use crate::config::UIConfig;
+use chrono::{DateTime, Local, TimeDelta};
+use parking_lot::{Mutex, RwLock};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+use crate::config::LogFileConfig;
 
-#[derive(Clone, Copy, Debug)]
+// A message repeating within this long of its last occurrence is counted on the existing entry
+const REPEAT_WINDOW: TimeDelta = TimeDelta::seconds(30);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum LogLevel {
     Info,
     Success,
     Warning,
     Error,
     Task,
+    // Per-click detail, kept apart from the other levels so it can't crowd them out
+    Trace,
 }
 
 impl LogLevel {
-    pub fn color(&self) -> Color {
+    // Stable lowercase name used in log files and exports
+    pub fn name(&self) -> &'static str {
         match self {
-            LogLevel::Info => Color::Blue,
-            LogLevel::Success => Color::Green,
-            LogLevel::Warning => Color::Yellow,
-            LogLevel::Error => Color::Red,
-            LogLevel::Task => Color::Cyan,
+            LogLevel::Info => "info",
+            LogLevel::Success => "success",
+            LogLevel::Warning => "warning",
+            LogLevel::Error => "error",
+            LogLevel::Task => "task",
+            LogLevel::Trace => "trace",
         }
     }
 
@@ -30,44 +43,222 @@ impl LogLevel {
             LogLevel::Warning => "⚡",
             LogLevel::Error => "❌",
             LogLevel::Task => "🔧",
+            LogLevel::Trace => "🔍",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
         }
     }
 }
 
 #[derive(Clone)]
 pub struct LogEntry {
+    // When the message was last logged
     pub timestamp: DateTime<Local>,
     pub level: LogLevel,
     pub message: String,
+    // Times the message was logged in a row, collapsed into this entry
+    pub repeats: u32,
+}
+
+impl LogEntry {
+    // The message with its repeat count, e.g. `Click failed (x12)`
+    pub fn text(&self) -> String {
+        if self.repeats > 1 {
+            format!("{} (x{})", self.message, self.repeats)
+        } else {
+            self.message.clone()
+        }
+    }
+
+    fn repeated_by(&self, level: LogLevel, message: &str, at: DateTime<Local>) -> bool {
+        self.level == level && self.message == message && at - self.timestamp <= REPEAT_WINDOW
+    }
 }
 
 pub struct Logger {
     entries: RwLock<Vec<LogEntry>>,
+    // Trace entries, with their own retention, so a fast stream of them doesn't evict the rest
+    traces: RwLock<Vec<LogEntry>>,
+    retention: usize,
+    file: Option<Mutex<LogFile>>,
 }
 
 impl Logger {
-    pub fn new() -> Self {
+    pub fn new(retention: usize, file_config: &LogFileConfig) -> Self {
+        // A log file that can't be opened shouldn't stop the bot; the UI log still works
+        let file = if file_config.enabled {
+            match LogFile::open(file_config) {
+                Ok(file) => Some(Mutex::new(file)),
+                Err(e) => {
+                    eprintln!("Failed to open log file {}: {}", file_config.path, e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
         Self {
             entries: RwLock::new(Vec::new()),
+            traces: RwLock::new(Vec::new()),
+            retention: retention.max(1),
+            file,
         }
     }
 
     pub fn log(&self, level: LogLevel, message: &str) {
-        let mut entries = self.entries.write();
-        entries.push(LogEntry {
-            timestamp: Local::now(),
+        let now = Local::now();
+        let mut entries = match level {
+            LogLevel::Trace => self.traces.write(),
+            _ => self.entries.write(),
+        };
+
+        // Collapse repeats of the last message instead of flooding the log. The file only gets
+        // a line each time the count doubles.
+        let last = entries.last_mut().filter(|last| last.repeated_by(level, message, now));
+        if let Some(last) = last {
+            last.timestamp = now;
+            last.repeats += 1;
+            if last.repeats.is_power_of_two() {
+                self.write_to_file(last);
+            }
+            return;
+        }
+
+        let entry = LogEntry {
+            timestamp: now,
             level,
             message: message.to_string(),
-        });
+            repeats: 1,
+        };
+        self.write_to_file(&entry);
+        entries.push(entry);
 
         // Keep only the last N entries
-        if entries.len() > UIConfig::MAX_LOGS {
-            let excess = entries.len().saturating_sub(UIConfig::MAX_LOGS);
+        if entries.len() > self.retention {
+            let excess = entries.len().saturating_sub(self.retention);
             entries.drain(0..excess);
         }
     }
 
+    fn write_to_file(&self, entry: &LogEntry) {
+        if let Some(file) = &self.file {
+            let mut file = file.lock();
+            if entry.level != LogLevel::Trace || file.trace {
+                let _ = file.write_entry(entry);
+            }
+        }
+    }
+
+    // Every level except trace
     pub fn get_entries(&self) -> Vec<LogEntry> {
         self.entries.read().clone()
     }
-}
\ No newline at end of file
+
+    pub fn get_traces(&self) -> Vec<LogEntry> {
+        self.traces.read().clone()
+    }
+
+    // Writes every buffered entry to `path`, returning how many were written
+    pub fn export(&self, path: impl AsRef<Path>, format: ExportFormat) -> Result<usize> {
+        let path = path.as_ref();
+        let entries = self.get_entries();
+
+        let contents = match format {
+            ExportFormat::Json => {
+                let rows: Vec<_> = entries
+                    .iter()
+                    .map(|entry| {
+                        serde_json::json!({
+                            "timestamp": entry.timestamp.to_rfc3339(),
+                            "level": entry.level.name(),
+                            "message": entry.message,
+                            "repeats": entry.repeats,
+                        })
+                    })
+                    .collect();
+                serde_json::to_string_pretty(&rows)?
+            }
+            ExportFormat::Csv => {
+                let mut csv = String::from("timestamp,level,message,repeats\n");
+                for entry in &entries {
+                    csv.push_str(&format!(
+                        "{},{},\"{}\",{}\n",
+                        entry.timestamp.to_rfc3339(),
+                        entry.level.name(),
+                        entry.message.replace('"', "\"\""),
+                        entry.repeats
+                    ));
+                }
+                csv
+            }
+        };
+
+        fs::write(path, contents).with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(entries.len())
+    }
+}
+struct LogFile {
+    path: PathBuf,
+    max_bytes: u64,
+    // Whether trace entries are written too
+    trace: bool,
+    file: File,
+    size: u64,
+}
+
+impl LogFile {
+    fn open(config: &LogFileConfig) -> io::Result<Self> {
+        let path = PathBuf::from(&config.path);
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path,
+            max_bytes: config.max_bytes,
+            trace: config.trace,
+            file,
+            size,
+        })
+    }
+
+    fn write_entry(&mut self, entry: &LogEntry) -> io::Result<()> {
+        let line = format!(
+            "{} [{}] {}\n",
+            entry.timestamp.format("%Y-%m-%d %H:%M:%S%.3f"),
+            entry.level.name(),
+            entry.text()
+        );
+
+        if self.max_bytes > 0 && self.size + line.len() as u64 > self.max_bytes && self.size > 0 {
+            self.rotate()?;
+        }
+        self.file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    // Keeps a single previous generation at `<path>.1`
+    fn rotate(&mut self) -> io::Result<()> {
+        let mut rotated = self.path.clone().into_os_string();
+        rotated.push(".1");
+        fs::rename(&self.path, rotated)?;
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
@@ -0,0 +1,144 @@
+use crate::types::{ClickStep, CoordMode, Position};
+use anyhow::{Context, Result};
+use device_query::{DeviceQuery, DeviceState, Keycode};
+use enigo::Button;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path, time::Duration};
+use tokio::time::Instant;
+
+// How often the mouse is sampled while recording; fast enough not to miss a short click
+const RECORD_POLL: Duration = Duration::from_millis(10);
+
+// A recorded click sequence, saved as TOML:
+//
+//   [[steps]]
+//   delay_ms = 350
+//   click = { x = 830, y = 300 }
+//
+//   [[steps]]
+//   delay_ms = 200
+//   scroll = { x = 900, y = 600, amount = -3 }
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Macro {
+    pub steps: Vec<MacroStep>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MacroStep {
+    // Wait before this step, measured from the previous one
+    #[serde(default)]
+    pub delay_ms: u64,
+    #[serde(flatten)]
+    pub action: MacroAction,
+}
+
+impl MacroStep {
+    pub fn delay(&self) -> Duration {
+        Duration::from_millis(self.delay_ms)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MacroAction {
+    Click(ClickStep),
+    // Wheel notches, positive scrolling up, with the cursor over `at`
+    Scroll {
+        #[serde(flatten)]
+        at: CoordMode,
+        amount: i32,
+    },
+}
+
+impl Macro {
+    pub fn load_from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read macro {}", path.display()))?;
+        toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse macro {}", path.display()))
+    }
+
+    pub fn save_to_file(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let contents = toml::to_string_pretty(self)?;
+        fs::write(path, contents)
+            .with_context(|| format!("Failed to write macro {}", path.display()))
+    }
+}
+
+// Records mouse clicks until Escape is pressed. `to_target` turns each screen position into the
+// target that gets saved, e.g. scaling it back to the reference resolution. Wheel movement
+// can't be read back from the system, so scroll steps have to be added to the file by hand.
+pub async fn record(
+    to_target: impl Fn(Position) -> CoordMode,
+    on_step: impl Fn(&MacroStep),
+) -> Macro {
+    let device = DeviceState::new();
+    let buttons = [(1, Button::Left), (2, Button::Right), (3, Button::Middle)];
+    let mut held = [false; 3];
+    let mut recorded = Macro::default();
+    let mut last_step = Instant::now();
+
+    while !device.get_keys().contains(&Keycode::Escape) {
+        let mouse = device.get_mouse();
+        for ((index, button), held) in buttons.into_iter().zip(held.iter_mut()) {
+            let pressed = mouse.button_pressed.get(index).copied().unwrap_or(false);
+            if pressed && !*held {
+                let step = MacroStep {
+                    delay_ms: last_step.elapsed().as_millis() as u64,
+                    action: MacroAction::Click(ClickStep {
+                        at: to_target(Position::from(mouse.coords)),
+                        button,
+                        count: 1,
+                    }),
+                };
+                on_step(&step);
+                recorded.steps.push(step);
+                last_step = Instant::now();
+            }
+            *held = pressed;
+        }
+        tokio::time::sleep(RECORD_POLL).await;
+    }
+    recorded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_toml() {
+        let recorded = Macro {
+            steps: vec![
+                MacroStep {
+                    delay_ms: 350,
+                    action: MacroAction::Click(Position::new(830, 300).into()),
+                },
+                MacroStep {
+                    delay_ms: 0,
+                    action: MacroAction::Scroll {
+                        at: CoordMode::Percent {
+                            xp: 0.5,
+                            yp: 0.5,
+                            wp: 0.0,
+                            hp: 0.0,
+                        },
+                        amount: -3,
+                    },
+                },
+            ],
+        };
+        let text = toml::to_string_pretty(&recorded).unwrap();
+        assert_eq!(toml::from_str::<Macro>(&text).unwrap(), recorded);
+
+        let written: Macro =
+            toml::from_str("[[steps]]\nclick = { x = 1, y = 2, button = \"Right\" }\n").unwrap();
+        assert_eq!(written.steps[0].delay_ms, 0);
+        assert!(matches!(
+            written.steps[0].action,
+            MacroAction::Click(ClickStep { button: Button::Right, .. })
+        ));
+    }
+}
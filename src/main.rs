@@ -1,9 +1,23 @@
+mod alarm;
+mod backend;
+mod battery;
+mod clock;
 mod config;
+mod control;
+mod cpm_log;
 mod bot;
+mod focus;
 mod ui;
 mod stats;
 mod logger;
+mod notify;
+mod health;
+mod history;
 mod input;
+mod macros;
+mod pixel;
+mod schedule;
+mod status;
 mod types;
 
 use anyhow::Result;
@@ -11,97 +25,333 @@ use crossterm::{
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use std::{
-    io,
-    sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc,
-    },
-    time::Duration,
-};
+use std::{io, sync::Arc, time::Duration};
+use chrono::Local;
+use device_query::{DeviceQuery, DeviceState};
+use tokio::task::JoinHandle;
+
+use crate::bot::{Bot, BotSnapshot};
+use crate::config::{Config, ExitBehavior, CONFIG_PATH, SESSIONS_DIR};
+use crate::control::ControlServer;
+use crate::cpm_log::CpmRecorder;
+use crate::ui::{format_duration, QuitPrompt, UI};
+use crate::input::{keycode_from_terminal, modifiers_from_terminal, InputHandler};
+use crate::logger::LogLevel;
+use crate::macros::MacroAction;
+use crate::status::StatusWriter;
+use crate::types::Position;
 
-use crate::bot::Bot;
-use crate::ui::UI;
-use crate::input::InputHandler;
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+const HEADLESS_STATUS_INTERVAL: Duration = Duration::from_secs(10);
+const DEFAULT_MACRO_PATH: &str = "macro.toml";
 
 pub struct App {
     bot: Arc<Bot>,
-    should_quit: AtomicBool,
+    config: Config,
 }
 
 impl App {
-    pub fn new() -> Self {
+    pub fn new(config: Config, dry_run: bool) -> Self {
         Self {
-            bot: Arc::new(Bot::new()),
-            should_quit: AtomicBool::new(false),
+            bot: Arc::new(Bot::new(config.clone(), dry_run)),
+            config,
         }
     }
 
-    pub async fn run(&self) -> Result<()> {
-        // Setup terminal
-        enable_raw_mode()?;
-        let mut stdout = io::stdout();
-        execute!(stdout, EnterAlternateScreen)?;
+    pub async fn run(&self, headless: bool) -> Result<()> {
+        // Setup terminal; headless mode leaves it alone and prints plain status lines instead
+        let mut ui = if headless {
+            None
+        } else {
+            install_panic_hook();
+            enable_raw_mode()?;
+            let mut stdout = io::stdout();
+            execute!(stdout, EnterAlternateScreen)?;
+            Some(UI::new(stdout, self.config.display.theme())?)
+        };
 
         // Start bot loop
         let bot = self.bot.clone();
-        tokio::spawn(async move {
+        let bot_task = tokio::spawn(async move {
+            // Printing would scribble over the TUI, so errors go to the activity log
             if let Err(e) = bot.run_loop().await {
-                eprintln!("Bot error: {}", e);
+                bot.get_logger().log(LogLevel::Error, &format!("Bot stopped: {:#}", e));
             }
         });
 
         // Start input handler
         let bot = self.bot.clone();
         let input_handler = InputHandler::new(bot);
-        tokio::spawn(async move {
+        let input_task = tokio::spawn(async move {
             input_handler.run().await;
         });
+        let mut tasks = vec![bot_task, input_task];
 
-        // Run UI
-        let mut ui = UI::new(stdout)?;
-        let res = self.run_ui(&mut ui).await;
+        // Optional remote control over a loopback socket
+        if self.config.control.enabled {
+            let server = ControlServer::new(self.bot.clone(), self.config.control.clone());
+            tasks.push(tokio::spawn(async move {
+                server.run().await;
+            }));
+        }
+
+        let res = match &mut ui {
+            Some(ui) => self.run_ui(ui).await,
+            None => self.run_headless().await,
+        };
+
+        // Stop the background tasks before handing the terminal back
+        self.bot.shutdown();
+        for task in tasks {
+            stop_task(task).await;
+        }
 
         // Cleanup
-        disable_raw_mode()?;
-        execute!(ui.terminal.backend_mut(), LeaveAlternateScreen)?;
+        if let Some(ui) = &mut ui {
+            disable_raw_mode()?;
+            execute!(ui.terminal.backend_mut(), LeaveAlternateScreen)?;
+        }
+        self.bot.save_stats();
+        if res.is_ok() {
+            self.write_session_summary();
+        }
+        run_exit_actions(&self.config.exit, || self.save_config(), || self.print_summary());
         
         res
     }
 
+    fn write_session_summary(&self) {
+        match self.bot.get_stats().session_summary().save_to_dir(SESSIONS_DIR) {
+            Ok(path) => println!("Session summary written to {}", path.display()),
+            Err(e) => eprintln!("Failed to write session summary: {:#}", e),
+        }
+    }
+
+    fn save_config(&self) {
+        match self.config.save_to_file(CONFIG_PATH) {
+            Ok(()) => println!("Config saved to {}", CONFIG_PATH),
+            Err(e) => eprintln!("Failed to save config: {:#}", e),
+        }
+    }
+
+    fn print_summary(&self) {
+        let stats = self.bot.get_stats();
+        println!("\nSession summary");
+        println!("  Runtime: {}", format_duration(stats.get_runtime()));
+        println!("  Clicks:  {}", stats.get_clicks());
+        println!("  CPM:     {}", stats.get_cpm());
+    }
+
     async fn run_ui(&self, ui: &mut UI) -> Result<()> {
         let mut last_tick = tokio::time::Instant::now();
-        let tick_rate = Duration::from_millis(100);
+        let tick_rate = self.config.display.tick_rate();
+        let mut quit_pending = false;
+        // First ESC of a double tap, forgotten once the window passes
+        let mut esc_pressed: Option<tokio::time::Instant> = None;
+        let double_tap = self.config.exit.double_tap();
+        let mut status_writer = StatusWriter::new(self.config.status_file.clone());
+        let mut cpm_recorder = CpmRecorder::new(&self.config.cpm_log, &self.bot.get_logger());
+        let mouse_device = self.config.tuning.show_mouse.then(DeviceState::new);
 
-        loop {
-            ui.draw(&self.bot)?;
+        while !self.bot.is_shutting_down() {
+            if let Some(device) = &mouse_device {
+                ui.set_mouse_position(Position::from(device.get_mouse().coords));
+            }
+            esc_pressed =
+                esc_pressed.filter(|at| double_tap.is_some_and(|window| at.elapsed() <= window));
+            let quit_prompt = match (quit_pending, esc_pressed) {
+                (true, _) => QuitPrompt::Confirm,
+                (false, Some(_)) => QuitPrompt::PressAgain,
+                (false, None) => QuitPrompt::None,
+            };
+            ui.draw(&self.bot, quit_prompt)?;
+            status_writer.maybe_write(&self.bot.snapshot(), &self.bot.get_logger());
 
             let timeout = tick_rate.saturating_sub(last_tick.elapsed());
 
             if crossterm::event::poll(timeout)? {
                 if let crossterm::event::Event::Key(key) = crossterm::event::read()? {
                     use crossterm::event::KeyCode;
+
+                    if quit_pending {
+                        // Any key other than ESC/Y cancels the pending quit
+                        quit_pending = false;
+                        if matches!(key.code, KeyCode::Esc | KeyCode::Char('y' | 'Y')) {
+                            break;
+                        }
+                        continue;
+                    }
+
                     match key.code {
+                        KeyCode::Esc if self.config.exit.confirm => quit_pending = true,
+                        KeyCode::Esc if double_tap.is_some() && esc_pressed.is_none() => {
+                            esc_pressed = Some(tokio::time::Instant::now());
+                        }
                         KeyCode::Esc => {
-                            self.should_quit.store(true, Ordering::Relaxed);
                             break;
                         }
-                        KeyCode::F(1) => self.bot.toggle(),
-                        KeyCode::F(2) => self.bot.toggle_upgrades(),
-                        KeyCode::F(3) => self.bot.toggle_souls(),
-                        KeyCode::F(4) => self.bot.toggle_prestige(),
-                        _ => {}
+                        code => {
+                            let keybindings = &self.bot.profile().keybindings;
+                            let action = keycode_from_terminal(code)
+                                .and_then(|key| keybindings.action_for(key));
+                            let run_now = keybindings
+                                .run_now
+                                .is_held(&modifiers_from_terminal(key.modifiers));
+                            match (action, code) {
+                                (Some(action), _) if run_now => self.bot.perform_action_now(action),
+                                (Some(action), _) => self.bot.perform_action(action),
+                                (None, KeyCode::Up) => ui.scroll_logs(1),
+                                (None, KeyCode::Down) => ui.scroll_logs(-1),
+                                (None, KeyCode::PageUp) => ui.scroll_logs(10),
+                                (None, KeyCode::PageDown) => ui.scroll_logs(-10),
+                                (None, KeyCode::End) => ui.follow_logs(),
+                                (None, KeyCode::Tab) => ui.cycle_log_filter(),
+                                (None, KeyCode::Char('t' | 'T')) => ui.toggle_task_table(),
+                                _ => {}
+                            }
+                        }
                     }
                 }
             }
 
             if last_tick.elapsed() >= tick_rate {
+                self.sample_cpm(&mut cpm_recorder);
                 last_tick = tokio::time::Instant::now();
             }
         }
 
         Ok(())
     }
+
+    // Runs without the TUI until the kill switch or Ctrl+C; hotkeys still work through the
+    // global input handler
+    async fn run_headless(&self) -> Result<()> {
+        println!("Running headless - use the hotkeys to control the bot, Ctrl+C to quit");
+        let mut status_writer = StatusWriter::new(self.config.status_file.clone());
+        let mut cpm_recorder = CpmRecorder::new(&self.config.cpm_log, &self.bot.get_logger());
+        let mut tick = tokio::time::interval(self.config.display.tick_rate());
+        let mut status_line = tokio::time::interval(HEADLESS_STATUS_INTERVAL);
+        let ctrl_c = tokio::signal::ctrl_c();
+        tokio::pin!(ctrl_c);
+
+        while !self.bot.is_shutting_down() {
+            tokio::select! {
+                _ = tick.tick() => {
+                    self.sample_cpm(&mut cpm_recorder);
+                    status_writer.maybe_write(&self.bot.snapshot(), &self.bot.get_logger());
+                }
+                _ = status_line.tick() => println!("{}", format_status_line(&self.bot.snapshot())),
+                _ = &mut ctrl_c => break,
+            }
+        }
+
+        Ok(())
+    }
+
+    // The UI tick runs faster than once a second; stats only take a sample when one is due
+    fn sample_cpm(&self, cpm_recorder: &mut CpmRecorder) {
+        if let Some(cpm) = self.bot.get_stats().sample_cpm() {
+            cpm_recorder.record(cpm, &self.bot.get_logger());
+        }
+    }
+}
+
+// Runs whichever exit steps `exit` turns on, in order
+fn run_exit_actions(exit: &ExitBehavior, save_config: impl FnOnce(), print_summary: impl FnOnce()) {
+    if exit.save_config {
+        save_config();
+    }
+    if exit.print_summary {
+        print_summary();
+    }
+}
+
+fn format_status_line(snapshot: &BotSnapshot) -> String {
+    let next = snapshot
+        .tasks
+        .iter()
+        .filter(|task| task.enabled)
+        .min_by_key(|task| task.secs_until_next)
+        .map_or_else(
+            || "no tasks enabled".to_string(),
+            |task| format!("next {} in {}s", task.name, task.secs_until_next),
+        );
+
+    format!(
+        "[{}] {} | {} clicks | {} CPM | {}",
+        Local::now().format("%H:%M:%S"),
+        match (snapshot.input_available, snapshot.mining_active, snapshot.tasks_active) {
+            (false, _, _) => "INPUT UNAVAILABLE",
+            (true, true, true) => "ACTIVE",
+            (true, true, false) => "MINING ONLY",
+            (true, false, true) => "TASKS ONLY",
+            (true, false, false) => "PAUSED",
+        },
+        snapshot.clicks,
+        snapshot.cpm,
+        next
+    )
+}
+
+// Hands the terminal back before the default hook prints, so a panic doesn't leave it in raw mode
+// on the alternate screen
+fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = disable_raw_mode();
+        let _ = execute!(io::stdout(), LeaveAlternateScreen);
+        default_hook(info);
+    }));
+}
+
+// Waits for a task to notice shutdown, aborting it if it's stuck mid-action
+async fn stop_task(mut task: JoinHandle<()>) {
+    if tokio::time::timeout(SHUTDOWN_TIMEOUT, &mut task).await.is_err() {
+        task.abort();
+    }
+}
+
+// Moves the cursor over each configured position in turn instead of running the bot
+async fn verify_positions(config: Config, dry_run: bool, click: bool) -> Result<()> {
+    println!(
+        "Verifying positions ({}) - watch the cursor in the game window\n",
+        if click { "clicking each one" } else { "no clicks" }
+    );
+    let bot = Bot::new(config, dry_run);
+    let result = bot
+        .verify_positions(click, |name, pos| match pos {
+            Some(pos) => println!("  {:<32} ({}, {})", name, pos.x, pos.y),
+            None => println!("  {:<32} skipped, needs the screen size", name),
+        })
+        .await;
+
+    for entry in bot.get_logger().get_entries() {
+        if matches!(entry.level, LogLevel::Warning | LogLevel::Error) {
+            println!("{} {}", entry.level.icon(), entry.text());
+        }
+    }
+    result
+}
+
+// Records mouse clicks into a macro file for the macro task to play back
+async fn record_macro(config: Config, dry_run: bool, path: &str) -> Result<()> {
+    println!("Recording clicks into {} - press Escape to stop\n", path);
+    let bot = Bot::new(config, dry_run);
+    let recorded = bot
+        .record_macro(|step| match &step.action {
+            MacroAction::Click(click) => {
+                println!("  +{:>6}ms  {:?} click at {:?}", step.delay_ms, click.button, click.at)
+            }
+            MacroAction::Scroll { at, amount } => {
+                println!("  +{:>6}ms  scroll {} at {:?}", step.delay_ms, amount, at)
+            }
+        })
+        .await?;
+
+    recorded.save_to_file(path)?;
+    println!("\nSaved {} steps to {}", recorded.steps.len(), path);
+    println!("Set tasks.macro_file = \"{}\" to play it as the macro task", path);
+    Ok(())
 }
 
 #[tokio::main]
@@ -109,9 +359,63 @@ async fn main() -> Result<()> {
     println!("\n⛏️  IDLE CAVE MINER BOT v2.0\n");
     println!("Starting up...\n");
 
-    let app = App::new();
-    app.run().await?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let dry_run = args.iter().any(|arg| arg == "--dry-run");
+    let headless = args.iter().any(|arg| arg == "--headless");
+    if dry_run {
+        println!("Dry run: no clicks or scrolls will be sent\n");
+    }
+
+    let config = Config::load_from_file(CONFIG_PATH)?;
+    let verify = args.iter().find_map(|arg| match arg.as_str() {
+        "--verify-positions" => Some(false),
+        "--verify-positions=click" => Some(true),
+        _ => None,
+    });
+    if let Some(click) = verify {
+        return verify_positions(config, dry_run, click).await;
+    }
+    let record = args.iter().find_map(|arg| match arg.as_str() {
+        "--record-macro" => Some(DEFAULT_MACRO_PATH),
+        _ => arg.strip_prefix("--record-macro="),
+    });
+    if let Some(path) = record {
+        return record_macro(config, dry_run, path).await;
+    }
+
+    let app = App::new(config, dry_run);
+    app.run(headless).await?;
 
     println!("\nGoodbye!");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    #[test]
+    fn exit_actions_follow_each_config_combination() {
+        let combinations = [
+            (false, false, vec![]),
+            (true, false, vec!["save"]),
+            (false, true, vec!["summary"]),
+            (true, true, vec!["save", "summary"]),
+        ];
+        for (save_config, print_summary, expected) in combinations {
+            let exit = ExitBehavior {
+                save_config,
+                print_summary,
+                ..ExitBehavior::default()
+            };
+            let ran = RefCell::new(Vec::new());
+            run_exit_actions(
+                &exit,
+                || ran.borrow_mut().push("save"),
+                || ran.borrow_mut().push("summary"),
+            );
+            assert_eq!(ran.into_inner(), expected, "save {} summary {}", save_config, print_summary);
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use crate::config::{NotifyConfig, NotifyEvent, APP_NAME};
+use std::{
+    io::{self, Write},
+    process::{Command, Stdio},
+};
+
+// Sends the configured bell and desktop notifications for notable events
+pub struct Notifier {
+    config: NotifyConfig,
+}
+
+impl Notifier {
+    pub fn new(config: NotifyConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn notify(&self, event: NotifyEvent, message: &str) {
+        if !self.config.events.contains(&event) {
+            return;
+        }
+        if self.config.bell {
+            ring_bell();
+        }
+        if self.config.desktop {
+            send_desktop(message);
+        }
+    }
+}
+
+pub fn ring_bell() {
+    let mut stdout = io::stdout();
+    let _ = stdout.write_all(b"\x07");
+    let _ = stdout.flush();
+}
+
+// Best effort through `notify-send`; quietly does nothing where it isn't installed
+fn send_desktop(message: &str) {
+    let child = Command::new("notify-send")
+        .arg(APP_NAME)
+        .arg(message)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    // Reap it off the bot's thread so it doesn't linger as a zombie
+    if let Ok(mut child) = child {
+        std::thread::spawn(move || child.wait());
+    }
+}
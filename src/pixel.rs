@@ -0,0 +1,59 @@
+use crate::types::Position;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+// A screen color, written as `[r, g, b]` in the config
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    // Largest per-channel difference
+    pub fn distance(self, other: Rgb) -> u8 {
+        self.0
+            .abs_diff(other.0)
+            .max(self.1.abs_diff(other.1))
+            .max(self.2.abs_diff(other.2))
+    }
+}
+
+pub fn is_supported() -> bool {
+    cfg!(feature = "pixel-check")
+}
+
+#[cfg(feature = "pixel-check")]
+pub fn sample(pos: Position) -> Result<Rgb> {
+    use xcap::Monitor;
+
+    let monitor = Monitor::from_point(pos.x, pos.y)?;
+    let x = (pos.x - monitor.x()?).max(0) as u32;
+    let y = (pos.y - monitor.y()?).max(0) as u32;
+    let image = monitor.capture_region(x, y, 1, 1)?;
+    let [r, g, b, _] = image.get_pixel(0, 0).0;
+    Ok(Rgb(r, g, b))
+}
+
+#[cfg(not(feature = "pixel-check"))]
+pub fn sample(_pos: Position) -> Result<Rgb> {
+    anyhow::bail!("built without the pixel-check feature")
+}
+
+// Hash of a screen region's pixels, for spotting when it stops changing
+#[cfg(feature = "pixel-check")]
+pub fn region_hash(origin: Position, width: u32, height: u32) -> Result<u64> {
+    use std::hash::{Hash, Hasher};
+    use xcap::Monitor;
+
+    let monitor = Monitor::from_point(origin.x, origin.y)?;
+    let x = (origin.x - monitor.x()?).max(0) as u32;
+    let y = (origin.y - monitor.y()?).max(0) as u32;
+    let image = monitor.capture_region(x, y, width.max(1), height.max(1))?;
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    image.as_raw().hash(&mut hasher);
+    Ok(hasher.finish())
+}
+
+#[cfg(not(feature = "pixel-check"))]
+pub fn region_hash(_origin: Position, _width: u32, _height: u32) -> Result<u64> {
+    anyhow::bail!("built without the pixel-check feature")
+}
@@ -0,0 +1,150 @@
+use crate::config::ScheduleConfig;
+use chrono::{Local, NaiveTime};
+
+pub enum ScheduleEvent {
+    Start,
+    Stop,
+}
+
+// Turns the configured wall-clock window into start/stop events as the clock crosses its edges
+pub struct Schedule {
+    start: Option<NaiveTime>,
+    end: Option<NaiveTime>,
+    last_checked: Option<NaiveTime>,
+}
+
+impl Schedule {
+    // Times are checked by `Config::validate`, so bad ones are simply ignored here
+    pub fn new(config: &ScheduleConfig) -> Self {
+        let (start, end) = config.times().unwrap_or_default();
+        Self {
+            start,
+            end,
+            last_checked: None,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.start.is_some() || self.end.is_some()
+    }
+
+    pub fn check(&mut self) -> Option<ScheduleEvent> {
+        self.check_at(Local::now().time())
+    }
+
+    pub fn check_at(&mut self, now: NaiveTime) -> Option<ScheduleEvent> {
+        if !self.is_enabled() {
+            return None;
+        }
+
+        let Some(previous) = self.last_checked.replace(now) else {
+            // Launched inside a full window: start right away instead of waiting a day
+            return match (self.start, self.end) {
+                (Some(start), Some(end)) if in_window(start, end, now) => Some(ScheduleEvent::Start),
+                _ => None,
+            };
+        };
+
+        if self.end.is_some_and(|end| crossed(previous, now, end)) {
+            Some(ScheduleEvent::Stop)
+        } else if self.start.is_some_and(|start| crossed(previous, now, start)) {
+            Some(ScheduleEvent::Start)
+        } else {
+            None
+        }
+    }
+}
+
+// Whether the clock passed `edge` on its way from `previous` to `now`, allowing for midnight
+fn crossed(previous: NaiveTime, now: NaiveTime, edge: NaiveTime) -> bool {
+    if previous <= now {
+        previous < edge && edge <= now
+    } else {
+        edge > previous || edge <= now
+    }
+}
+
+// A window whose end is earlier than its start runs over midnight, e.g. 22:00 to 06:00
+fn in_window(start: NaiveTime, end: NaiveTime, now: NaiveTime) -> bool {
+    if start <= end {
+        start <= now && now < end
+    } else {
+        now >= start || now < end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schedule(start: Option<&str>, end: Option<&str>) -> Schedule {
+        Schedule::new(&ScheduleConfig {
+            start: start.map(str::to_string),
+            end: end.map(str::to_string),
+        })
+    }
+
+    // Checks the schedule at each `HH:MM` in turn, naming the event each check produced
+    fn run(schedule: &mut Schedule, times: &[&str]) -> Vec<&'static str> {
+        times
+            .iter()
+            .map(|time| {
+                let now = NaiveTime::parse_from_str(time, "%H:%M").unwrap();
+                match schedule.check_at(now) {
+                    Some(ScheduleEvent::Start) => "start",
+                    Some(ScheduleEvent::Stop) => "stop",
+                    None => "-",
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fires_on_each_edge_once() {
+        let mut schedule = schedule(Some("09:00"), Some("17:00"));
+        let times = ["08:00", "08:59", "09:00", "09:01", "16:59", "17:00", "17:01"];
+        assert_eq!(run(&mut schedule, &times), ["-", "-", "start", "-", "-", "stop", "-"]);
+    }
+
+    #[test]
+    fn window_can_run_over_midnight() {
+        let mut schedule = schedule(Some("22:00"), Some("06:00"));
+        let times =
+            ["21:30", "22:00", "23:59", "00:00", "05:59", "06:00", "12:00", "21:59", "22:01"];
+        assert_eq!(
+            run(&mut schedule, &times),
+            ["-", "start", "-", "-", "-", "stop", "-", "-", "start"]
+        );
+    }
+
+    #[test]
+    fn checks_that_skip_past_midnight_still_see_the_edge() {
+        let mut schedule = schedule(Some("23:00"), Some("00:30"));
+        assert_eq!(run(&mut schedule, &["22:50", "23:10", "00:40"]), ["-", "start", "stop"]);
+    }
+
+    #[test]
+    fn launching_inside_a_window_starts_right_away() {
+        let mut day = schedule(Some("09:00"), Some("17:00"));
+        assert_eq!(run(&mut day, &["12:00", "12:01"]), ["start", "-"]);
+        let mut night = schedule(Some("22:00"), Some("06:00"));
+        assert_eq!(run(&mut night, &["03:00"]), ["start"]);
+        let mut outside = schedule(Some("09:00"), Some("17:00"));
+        assert_eq!(run(&mut outside, &["18:00"]), ["-"]);
+    }
+
+    #[test]
+    fn start_only_window_never_stops() {
+        let mut schedule = schedule(Some("09:00"), None);
+        // Launching after the start waits for the next day rather than guessing
+        let times = ["12:00", "23:59", "00:00", "08:59", "09:00", "12:00"];
+        assert_eq!(run(&mut schedule, &times), ["-", "-", "-", "-", "start", "-"]);
+    }
+
+    #[test]
+    fn no_times_disables_the_schedule() {
+        let mut schedule = schedule(None, None);
+        assert!(!schedule.is_enabled());
+        assert_eq!(run(&mut schedule, &["09:00", "17:00"]), ["-", "-"]);
+    }
+}
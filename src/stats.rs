@@ -1,22 +1,112 @@
+use crate::{
+    clock::{Clock, SystemClock},
+    types::{TaskOutcome, TaskType},
+};
+use anyhow::{Context, Result};
+use chrono::{Duration as ChronoDuration, Local};
 use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
 use std::{
-    sync::atomic::{AtomicU64, Ordering},
+    collections::{BTreeMap, HashMap, VecDeque},
+    fs,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
     time::{Duration, Instant},
 };
 
-pub struct Stats {
+const CPM_HISTORY_LEN: usize = 60;
+const CPM_SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+const CPM_WINDOW: Duration = Duration::from_secs(60);
+
+// Totals carried across sessions. Runtime is stored in seconds since `Instant` isn't serializable.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct LifetimeStats {
+    pub clicks: u64,
+    pub runtime_secs: u64,
+}
+
+// Written to `sessions/` when the app exits
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionSummary {
+    pub started_at: String,
+    pub ended_at: String,
+    pub runtime_secs: u64,
+    pub clicks: u64,
+    pub task_clicks: u64,
+    pub failed_actions: u64,
+    pub average_cpm: u64,
+    pub peak_cpm: u64,
+    pub task_runs: BTreeMap<&'static str, u64>,
+}
+
+impl SessionSummary {
+    // Writes `session_YYYYmmdd_HHMMSS.json` into `dir`, creating it if needed
+    pub fn save_to_dir(&self, dir: impl AsRef<Path>) -> Result<PathBuf> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        let path = dir.join(format!("session_{}.json", Local::now().format("%Y%m%d_%H%M%S")));
+        let contents = serde_json::to_string_pretty(self)?;
+        fs::write(&path, contents).with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    }
+}
+
+pub struct Stats<C: Clock = SystemClock> {
+    clock: C,
     clicks: AtomicU64,
+    task_clicks: AtomicU64,
+    failed_actions: AtomicU64,
     session_start: RwLock<Instant>,
+    // Set when the session is paused, so runtime and CPM stop counting until the next one
+    session_end: RwLock<Option<Instant>>,
+    // Totals from finished sessions; the open session is added on top when reading
+    lifetime: RwLock<LifetimeStats>,
+    session_open: AtomicBool,
+    dirty: AtomicBool,
+    // Recent once-per-second CPM samples for the UI graph, oldest first
+    cpm_history: RwLock<VecDeque<u64>>,
+    // Click counter snapshots, one per second, covering the rolling CPM window
+    click_samples: RwLock<VecDeque<(Instant, u64)>>,
+    peak_cpm: AtomicU64,
+    task_runs: RwLock<HashMap<TaskType, u64>>,
+    // Verified runs per task as (succeeded, failed); unverified runs only count in `task_runs`
+    task_outcomes: RwLock<HashMap<TaskType, (u64, u64)>>,
+    task_last_run: RwLock<HashMap<TaskType, Instant>>,
+    // CPM reads 0 until the session is this old, since a tiny window makes it swing wildly
+    warmup: Duration,
 }
 
-impl Stats {
-    pub fn new() -> Self {
+impl<C: Clock> Stats<C> {
+    pub fn with_clock(clock: C) -> Self {
         Self {
             clicks: AtomicU64::new(0),
-            session_start: RwLock::new(Instant::now()),
+            task_clicks: AtomicU64::new(0),
+            failed_actions: AtomicU64::new(0),
+            session_start: RwLock::new(clock.now()),
+            session_end: RwLock::new(None),
+            lifetime: RwLock::new(LifetimeStats::default()),
+            session_open: AtomicBool::new(false),
+            dirty: AtomicBool::new(false),
+            cpm_history: RwLock::new(VecDeque::with_capacity(CPM_HISTORY_LEN)),
+            click_samples: RwLock::new(VecDeque::new()),
+            peak_cpm: AtomicU64::new(0),
+            task_runs: RwLock::new(HashMap::new()),
+            task_outcomes: RwLock::new(HashMap::new()),
+            task_last_run: RwLock::new(HashMap::new()),
+            warmup: Duration::ZERO,
+            clock,
         }
     }
 
+    pub fn with_warmup(mut self, warmup: Duration) -> Self {
+        self.warmup = warmup;
+        self
+    }
+
+    pub fn in_warmup(&self) -> bool {
+        self.get_runtime() < self.warmup
+    }
+
     pub fn increment_clicks(&self) {
         self.clicks.fetch_add(1, Ordering::Relaxed);
     }
@@ -25,21 +115,300 @@ impl Stats {
         self.clicks.load(Ordering::Relaxed)
     }
 
+    pub fn increment_task_clicks(&self) {
+        self.task_clicks.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn get_task_clicks(&self) -> u64 {
+        self.task_clicks.load(Ordering::Relaxed)
+    }
+
+    pub fn increment_failed_actions(&self) {
+        self.failed_actions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn get_failed_actions(&self) -> u64 {
+        self.failed_actions.load(Ordering::Relaxed)
+    }
+
+    pub fn record_task_run(&self, task_type: TaskType, outcome: TaskOutcome) {
+        *self.task_runs.write().entry(task_type).or_insert(0) += 1;
+        self.task_last_run.write().insert(task_type, self.clock.now());
+
+        let mut outcomes = self.task_outcomes.write();
+        let (succeeded, failed) = outcomes.entry(task_type).or_insert((0, 0));
+        match outcome {
+            TaskOutcome::Succeeded => *succeeded += 1,
+            TaskOutcome::Failed => *failed += 1,
+            TaskOutcome::Ran => {}
+        }
+    }
+
+    pub fn get_task_runs(&self, task_type: TaskType) -> u64 {
+        self.task_runs.read().get(&task_type).copied().unwrap_or(0)
+    }
+
+    // Share of verified runs that succeeded, as a percentage; None until one has been verified
+    pub fn get_task_success_rate(&self, task_type: TaskType) -> Option<u64> {
+        let (succeeded, failed) =
+            self.task_outcomes.read().get(&task_type).copied().unwrap_or_default();
+        let verified = succeeded + failed;
+        (verified > 0).then(|| succeeded * 100 / verified)
+    }
+
+    // Time since the task last ran this session, if it has
+    pub fn get_task_last_run(&self, task_type: TaskType) -> Option<Duration> {
+        self.task_last_run.read().get(&task_type).map(|&at| self.clock.elapsed(at))
+    }
+
     pub fn get_cpm(&self) -> u64 {
-        let elapsed = self.session_start.read().elapsed().as_secs();
-        if elapsed == 0 {
+        let elapsed = self.get_runtime().as_secs();
+        if elapsed == 0 || self.in_warmup() {
             0
         } else {
             (self.get_clicks() * 60) / elapsed
         }
     }
 
+    // Snapshots the click counter if a second has passed since the last sample. Feeds the
+    // rolling CPM, the peak (rate over the last second) and the graph history. Returns the new
+    // rolling CPM when a sample was taken.
+    pub fn sample_cpm(&self) -> Option<u64> {
+        let mut samples = self.click_samples.write();
+        if samples.back().is_some_and(|&(at, _)| self.clock.elapsed(at) < CPM_SAMPLE_INTERVAL) {
+            return None;
+        }
+        let clicks = self.get_clicks();
+        if let Some(&(at, previous)) = samples.back() {
+            let rate = rate_per_minute(clicks, previous, self.clock.elapsed(at));
+            self.peak_cpm.fetch_max(rate, Ordering::Relaxed);
+        }
+        samples.push_back((self.clock.now(), clicks));
+        while samples.front().is_some_and(|&(at, _)| self.clock.elapsed(at) > CPM_WINDOW) {
+            samples.pop_front();
+        }
+        drop(samples);
+
+        let cpm = self.get_windowed_cpm();
+        let mut history = self.cpm_history.write();
+        if history.len() == CPM_HISTORY_LEN {
+            history.pop_front();
+        }
+        history.push_back(cpm);
+        Some(cpm)
+    }
+
+    // CPM over roughly the last minute; falls back to the session average before any samples
+    pub fn get_windowed_cpm(&self) -> u64 {
+        if self.in_warmup() {
+            return 0;
+        }
+        match self.click_samples.read().front() {
+            Some(&(at, clicks)) if !self.clock.elapsed(at).is_zero() => {
+                rate_per_minute(self.get_clicks(), clicks, self.clock.elapsed(at))
+            }
+            _ => self.get_cpm(),
+        }
+    }
+
+    pub fn get_peak_cpm(&self) -> u64 {
+        self.peak_cpm.load(Ordering::Relaxed)
+    }
+
+    pub fn get_cpm_history(&self) -> Vec<u64> {
+        self.cpm_history.read().iter().copied().collect()
+    }
+
     pub fn get_runtime(&self) -> Duration {
-        self.session_start.read().elapsed()
+        let start = *self.session_start.read();
+        match *self.session_end.read() {
+            Some(end) => end.saturating_duration_since(start),
+            None => self.clock.elapsed(start),
+        }
     }
 
     pub fn reset(&self) {
         self.clicks.store(0, Ordering::Relaxed);
-        *self.session_start.write() = Instant::now();
+        self.task_clicks.store(0, Ordering::Relaxed);
+        self.failed_actions.store(0, Ordering::Relaxed);
+        self.task_runs.write().clear();
+        self.task_outcomes.write().clear();
+        self.task_last_run.write().clear();
+        self.peak_cpm.store(0, Ordering::Relaxed);
+        self.click_samples.write().clear();
+        *self.session_start.write() = self.clock.now();
+        *self.session_end.write() = None;
+        self.session_open.store(true, Ordering::Relaxed);
     }
-}
\ No newline at end of file
+
+    pub fn session_summary(&self) -> SessionSummary {
+        let runtime = self.get_runtime();
+        let ended_at = Local::now();
+        let started_at = ended_at
+            - ChronoDuration::from_std(runtime).unwrap_or_else(|_| ChronoDuration::zero());
+
+        SessionSummary {
+            started_at: started_at.to_rfc3339(),
+            ended_at: ended_at.to_rfc3339(),
+            runtime_secs: runtime.as_secs(),
+            clicks: self.get_clicks(),
+            task_clicks: self.get_task_clicks(),
+            failed_actions: self.get_failed_actions(),
+            average_cpm: self.get_cpm(),
+            peak_cpm: self.get_peak_cpm(),
+            task_runs: TaskType::ALL
+                .iter()
+                .map(|&task_type| (task_type.name(), self.get_task_runs(task_type)))
+                .collect(),
+        }
+    }
+
+    // Folds the current session into the lifetime totals
+    pub fn end_session(&self) {
+        if !self.session_open.swap(false, Ordering::Relaxed) {
+            return;
+        }
+        *self.session_end.write() = Some(self.clock.now());
+        let mut lifetime = self.lifetime.write();
+        lifetime.clicks += self.get_clicks();
+        lifetime.runtime_secs += self.get_runtime().as_secs();
+        self.dirty.store(true, Ordering::Relaxed);
+    }
+
+    pub fn get_lifetime(&self) -> LifetimeStats {
+        let mut lifetime = *self.lifetime.read();
+        if self.session_open.load(Ordering::Relaxed) {
+            lifetime.clicks += self.get_clicks();
+            lifetime.runtime_secs += self.get_runtime().as_secs();
+        }
+        lifetime
+    }
+
+    // Clears the dirty flag, returning whether there was anything unsaved
+    pub fn take_dirty(&self) -> bool {
+        self.dirty.swap(false, Ordering::Relaxed)
+    }
+
+    pub fn save_to_disk(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let contents = serde_json::to_string_pretty(&self.get_lifetime())?;
+        fs::write(path, contents).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    pub fn load_from_disk(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(());
+        }
+
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        *self.lifetime.write() = serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        Ok(())
+    }
+}
+
+fn rate_per_minute(clicks: u64, since_clicks: u64, elapsed: Duration) -> u64 {
+    clicks.saturating_sub(since_clicks) * 60_000 / elapsed.as_millis().max(1) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FakeClock;
+
+    fn clicks(stats: &Stats<FakeClock>, count: u64) {
+        for _ in 0..count {
+            stats.increment_clicks();
+        }
+    }
+
+    #[test]
+    fn paused_session_stops_counting_runtime() {
+        let clock = FakeClock::new();
+        let stats = Stats::with_clock(clock.clone());
+        stats.reset();
+        for _ in 0..60 {
+            clock.advance(Duration::from_secs(1));
+            clicks(&stats, 2);
+        }
+        stats.end_session();
+
+        let before = stats.session_summary();
+        assert_eq!(before.runtime_secs, 60);
+        assert_eq!(before.average_cpm, 120);
+
+        clock.advance(Duration::from_secs(3600));
+        let after = stats.session_summary();
+        assert_eq!(after.runtime_secs, 60);
+        assert_eq!(after.average_cpm, 120);
+        assert_eq!(stats.get_runtime(), Duration::from_secs(60));
+        assert_eq!(stats.get_lifetime().runtime_secs, 60);
+
+        // The next session counts from its own start
+        stats.reset();
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(stats.get_runtime(), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn windowed_cpm_follows_recent_clicks() {
+        let clock = FakeClock::new();
+        let stats = Stats::with_clock(clock.clone());
+        stats.reset();
+        stats.sample_cpm();
+
+        for _ in 0..60 {
+            clock.advance(Duration::from_secs(1));
+            clicks(&stats, 10);
+            stats.sample_cpm();
+        }
+        assert_eq!(stats.get_windowed_cpm(), 600);
+        assert_eq!(stats.get_peak_cpm(), 600);
+        assert_eq!(stats.get_cpm(), 600);
+    }
+
+    #[test]
+    fn cpm_reads_zero_during_warmup() {
+        let clock = FakeClock::new();
+        let stats = Stats::with_clock(clock.clone()).with_warmup(Duration::from_secs(10));
+        stats.reset();
+
+        clock.advance(Duration::from_secs(5));
+        clicks(&stats, 50);
+        assert!(stats.in_warmup());
+        assert_eq!(stats.get_cpm(), 0);
+        assert_eq!(stats.get_windowed_cpm(), 0);
+
+        clock.advance(Duration::from_secs(5));
+        assert!(!stats.in_warmup());
+        assert_eq!(stats.get_cpm(), 300);
+    }
+
+    #[test]
+    fn success_rate_counts_only_verified_runs() {
+        let stats = Stats::with_clock(FakeClock::new());
+        stats.record_task_run(TaskType::Upgrades, TaskOutcome::Ran);
+        assert_eq!(stats.get_task_success_rate(TaskType::Upgrades), None);
+
+        stats.record_task_run(TaskType::Upgrades, TaskOutcome::Succeeded);
+        stats.record_task_run(TaskType::Upgrades, TaskOutcome::Succeeded);
+        stats.record_task_run(TaskType::Upgrades, TaskOutcome::Succeeded);
+        stats.record_task_run(TaskType::Upgrades, TaskOutcome::Failed);
+        assert_eq!(stats.get_task_runs(TaskType::Upgrades), 5);
+        assert_eq!(stats.get_task_success_rate(TaskType::Upgrades), Some(75));
+    }
+
+    #[test]
+    fn runtime_restarts_on_reset() {
+        let clock = FakeClock::new();
+        let stats = Stats::with_clock(clock.clone());
+        clock.advance(Duration::from_secs(90));
+        assert_eq!(stats.get_runtime(), Duration::from_secs(90));
+
+        stats.reset();
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(stats.get_runtime(), Duration::from_secs(5));
+    }
+}
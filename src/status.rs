@@ -0,0 +1,158 @@
+use crate::{
+    bot::BotSnapshot,
+    clock::{Clock, SystemClock},
+    config::StatusFileConfig,
+    logger::{LogLevel, Logger},
+};
+use anyhow::Result;
+use std::{fs, time::Instant};
+
+// Periodically writes the bot snapshot to a JSON file for overlay tools
+pub struct StatusWriter<C: Clock = SystemClock> {
+    clock: C,
+    config: StatusFileConfig,
+    last_write: Option<Instant>,
+    failing: bool,
+}
+
+impl StatusWriter {
+    pub fn new(config: StatusFileConfig) -> Self {
+        Self::with_clock(config, SystemClock)
+    }
+}
+
+impl<C: Clock> StatusWriter<C> {
+    pub fn with_clock(config: StatusFileConfig, clock: C) -> Self {
+        Self {
+            clock,
+            config,
+            last_write: None,
+            failing: false,
+        }
+    }
+
+    pub fn maybe_write(&mut self, snapshot: &BotSnapshot, logger: &Logger) {
+        if !self.config.enabled {
+            return;
+        }
+        if let Some(last) = self.last_write {
+            if self.clock.elapsed(last) < self.config.interval() {
+                return;
+            }
+        }
+        self.last_write = Some(self.clock.now());
+
+        // Only log the first failure of a streak so a bad path doesn't flood the log
+        match self.write(snapshot) {
+            Ok(()) => self.failing = false,
+            Err(e) if !self.failing => {
+                self.failing = true;
+                logger.log(LogLevel::Warning, &format!("Failed to write status file: {}", e));
+            }
+            Err(_) => {}
+        }
+    }
+
+    fn write(&self, snapshot: &BotSnapshot) -> Result<()> {
+        // Write to a temp file and rename so readers never see a partial file
+        let tmp_path = format!("{}.tmp", self.config.path);
+        fs::write(&tmp_path, serde_json::to_vec_pretty(snapshot)?)?;
+        fs::rename(&tmp_path, &self.config.path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bot::TaskSnapshot, clock::FakeClock, config::LogFileConfig, types::TaskType};
+    use serde_json::{json, Value};
+
+    #[test]
+    fn writes_the_snapshot_as_json() {
+        let dir = std::env::temp_dir().join(format!("status_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("status.json");
+        let mut writer = StatusWriter::new(StatusFileConfig {
+            enabled: true,
+            path: path.display().to_string(),
+            interval_ms: 1000,
+        });
+        let snapshot = BotSnapshot {
+            active: true,
+            mining_active: true,
+            tasks_active: false,
+            input_available: true,
+            clicks: 1234,
+            cpm: 600,
+            runtime_secs: 90,
+            tasks: vec![TaskSnapshot {
+                task: TaskType::Souls,
+                name: "Souls",
+                enabled: true,
+                secs_until_next: 42,
+                period_secs: 600,
+            }],
+        };
+
+        writer.maybe_write(&snapshot, &Logger::new(10, &LogFileConfig::default()));
+        let written: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        let leftover_tmp = dir.join("status.json.tmp").exists();
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(
+            written,
+            json!({
+                "active": true,
+                "mining_active": true,
+                "tasks_active": false,
+                "input_available": true,
+                "clicks": 1234,
+                "cpm": 600,
+                "runtime_secs": 90,
+                "tasks": [{
+                    "name": "Souls",
+                    "enabled": true,
+                    "secs_until_next": 42,
+                    "period_secs": 600,
+                }],
+            })
+        );
+        assert!(!leftover_tmp);
+    }
+
+    #[test]
+    fn waits_out_the_interval_between_writes() {
+        let dir = std::env::temp_dir().join(format!("status_interval_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("status.json");
+        let clock = FakeClock::new();
+        let config = StatusFileConfig {
+            enabled: true,
+            path: path.display().to_string(),
+            interval_ms: 1000,
+        };
+        let mut writer = StatusWriter::with_clock(config, clock.clone());
+        let snapshot = BotSnapshot {
+            active: false,
+            mining_active: false,
+            tasks_active: false,
+            input_available: true,
+            clicks: 0,
+            cpm: 0,
+            runtime_secs: 0,
+            tasks: Vec::new(),
+        };
+        let logger = Logger::new(10, &LogFileConfig::default());
+
+        let mut written = Vec::new();
+        for step_ms in [0, 400, 500, 100, 999, 1] {
+            clock.advance(std::time::Duration::from_millis(step_ms));
+            writer.maybe_write(&snapshot, &logger);
+            written.push(fs::remove_file(&path).is_ok());
+        }
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(written, [true, false, false, true, false, true]);
+    }
+}
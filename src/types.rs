@@ -1,4 +1,9 @@
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+use enigo::Button;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::ops::{Add, Sub};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub struct Position {
     pub x: i32,
     pub y: i32,
@@ -16,27 +21,394 @@ impl From<Position> for (i32, i32) {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+impl From<(i32, i32)> for Position {
+    fn from((x, y): (i32, i32)) -> Self {
+        Self { x, y }
+    }
+}
+
+impl Add for Position {
+    type Output = Position;
+
+    fn add(self, other: Position) -> Position {
+        Position::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl Sub for Position {
+    type Output = Position;
+
+    fn sub(self, other: Position) -> Position {
+        Position::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+// A configured target, either in absolute pixels or as fractions of the screen size. A width and
+// height make it a rectangle from (x, y), clicked at a random point inside each time.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum CoordMode {
+    Pixels {
+        x: i32,
+        y: i32,
+        #[serde(default, skip_serializing_if = "is_zero")]
+        width: i32,
+        #[serde(default, skip_serializing_if = "is_zero")]
+        height: i32,
+    },
+    Percent {
+        xp: f64,
+        yp: f64,
+        #[serde(default, skip_serializing_if = "is_zero")]
+        wp: f64,
+        #[serde(default, skip_serializing_if = "is_zero")]
+        hp: f64,
+    },
+}
+
+fn is_zero<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+impl CoordMode {
+    // Percent coordinates need the screen size; without it they can't be resolved. A rectangle
+    // resolves to its top-left corner.
+    pub fn resolve(&self, screen: Option<(i32, i32)>) -> Option<Position> {
+        match *self {
+            CoordMode::Pixels { x, y, .. } => Some(Position::new(x, y)),
+            CoordMode::Percent { xp, yp, .. } => {
+                let (width, height) = screen?;
+                Some(Position::new(
+                    (xp * width as f64).round() as i32,
+                    (yp * height as f64).round() as i32,
+                ))
+            }
+        }
+    }
+
+    pub fn has_negative_size(&self) -> bool {
+        match *self {
+            CoordMode::Pixels { width, height, .. } => width < 0 || height < 0,
+            CoordMode::Percent { wp, hp, .. } => wp < 0.0 || hp < 0.0,
+        }
+    }
+
+    // A percent rectangle reaching past 1.0 would pick points off the screen
+    pub fn overruns_screen(&self) -> bool {
+        match *self {
+            CoordMode::Pixels { .. } => false,
+            CoordMode::Percent { xp, yp, wp, hp } => xp + wp > 1.0 || yp + hp > 1.0,
+        }
+    }
+
+    // A uniformly random point inside the rectangle. Point targets come back unchanged without
+    // drawing from `rng`, so a seeded run clicks them exactly as before.
+    pub fn pick_within(&self, rng: &mut impl Rng) -> CoordMode {
+        match *self {
+            CoordMode::Pixels { x, y, width, height } if width > 0 || height > 0 => {
+                CoordMode::Pixels {
+                    x: x + pixel_offset(rng, width),
+                    y: y + pixel_offset(rng, height),
+                    width: 0,
+                    height: 0,
+                }
+            }
+            CoordMode::Percent { xp, yp, wp, hp } if wp > 0.0 || hp > 0.0 => CoordMode::Percent {
+                xp: xp + percent_offset(rng, wp),
+                yp: yp + percent_offset(rng, hp),
+                wp: 0.0,
+                hp: 0.0,
+            },
+            target => target,
+        }
+    }
+}
+
+// A rectangle `width` pixels wide covers offsets `0..width`; a side with no extent stays put
+fn pixel_offset(rng: &mut impl Rng, size: i32) -> i32 {
+    if size > 0 {
+        rng.gen_range(0..size)
+    } else {
+        0
+    }
+}
+
+fn percent_offset(rng: &mut impl Rng, size: f64) -> f64 {
+    if size > 0.0 {
+        rng.gen_range(0.0..size)
+    } else {
+        0.0
+    }
+}
+
+impl From<Position> for CoordMode {
+    fn from(pos: Position) -> Self {
+        CoordMode::Pixels {
+            x: pos.x,
+            y: pos.y,
+            width: 0,
+            height: 0,
+        }
+    }
+}
+
+// A click target with how to click it, e.g. `{ x = 830, y = 300, button = "Right", count = 2 }`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ClickStep {
+    #[serde(flatten)]
+    pub at: CoordMode,
+    #[serde(default)]
+    pub button: Button,
+    #[serde(default = "ClickStep::default_count")]
+    pub count: u32,
+}
+
+impl ClickStep {
+    fn default_count() -> u32 {
+        1
+    }
+}
+
+impl From<Position> for ClickStep {
+    fn from(pos: Position) -> Self {
+        Self {
+            at: pos.into(),
+            button: Button::Left,
+            count: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum TaskType {
     Upgrades,
     Souls,
+    Relics,
+    Forge,
+    Alchemy,
+    Miner,
+    Macro,
     Prestige,
 }
 
 impl TaskType {
+    pub const ALL: [TaskType; 8] = [
+        TaskType::Upgrades,
+        TaskType::Souls,
+        TaskType::Relics,
+        TaskType::Forge,
+        TaskType::Alchemy,
+        TaskType::Miner,
+        TaskType::Macro,
+        TaskType::Prestige,
+    ];
+
+    // Case-insensitive lookup by `name()`
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|task| task.name().eq_ignore_ascii_case(name))
+    }
+
     pub fn name(&self) -> &'static str {
         match self {
             TaskType::Upgrades => "Upgrades",
             TaskType::Souls => "Souls",
+            TaskType::Relics => "Relics",
+            TaskType::Forge => "Forge",
+            TaskType::Alchemy => "Alchemy",
+            TaskType::Miner => "Miner",
+            TaskType::Macro => "Macro",
             TaskType::Prestige => "Prestige",
         }
     }
 
+    // Higher runs first when several tasks come due together. Prestige resets the currency the
+    // other tasks would spend, so it goes first by default.
+    pub fn priority(&self) -> u8 {
+        match self {
+            TaskType::Prestige => 100,
+            TaskType::Alchemy | TaskType::Forge | TaskType::Relics | TaskType::Miner => 50,
+            TaskType::Souls | TaskType::Upgrades | TaskType::Macro => 10,
+        }
+    }
+
+    // Tasks that buy with the currency a prestige resets
+    pub fn spends_currency(&self) -> bool {
+        matches!(self, TaskType::Upgrades | TaskType::Souls | TaskType::Relics)
+    }
+
     pub fn icon(&self) -> &'static str {
         match self {
             TaskType::Upgrades => "🔧",
             TaskType::Souls => "👻",
+            TaskType::Relics => "💎",
+            TaskType::Forge => "🔥",
+            TaskType::Alchemy => "⚗️",
+            TaskType::Miner => "👷",
+            TaskType::Macro => "🎬",
             TaskType::Prestige => "⭐",
         }
     }
-}
\ No newline at end of file
+}
+
+// What a task run achieved, as far as the bot could tell. Runs without any screen verification
+// are just `Ran`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskOutcome {
+    Ran,
+    Succeeded,
+    Failed,
+}
+
+impl TaskOutcome {
+    // Combines the checks of a multi-step task: any failure fails it, otherwise any success counts
+    pub fn and(self, other: TaskOutcome) -> TaskOutcome {
+        match (self, other) {
+            (TaskOutcome::Failed, _) | (_, TaskOutcome::Failed) => TaskOutcome::Failed,
+            (TaskOutcome::Succeeded, _) | (_, TaskOutcome::Succeeded) => TaskOutcome::Succeeded,
+            _ => TaskOutcome::Ran,
+        }
+    }
+}
+
+// Hotkey-triggered actions, bound to keys through `Keybindings`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Toggle,
+    ToggleMining,
+    ToggleTasks,
+    Upgrades,
+    Souls,
+    Prestige,
+    DumpInput,
+    Relics,
+    Forge,
+    Alchemy,
+    ArmPrestige,
+    NextProfile,
+    ExportLog,
+    Miner,
+    Macro,
+}
+
+impl Action {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Action::Toggle => "Toggle",
+            Action::ToggleMining => "Mining",
+            Action::ToggleTasks => "Tasks",
+            Action::Upgrades => "Upgrades",
+            Action::Souls => "Souls",
+            Action::Prestige => "Prestige",
+            Action::DumpInput => "Dump Input",
+            Action::Relics => "Relics",
+            Action::Forge => "Forge",
+            Action::Alchemy => "Alchemy",
+            Action::ArmPrestige => "Arm Prestige",
+            Action::NextProfile => "Profile",
+            Action::ExportLog => "Export Log",
+            Action::Miner => "Miner",
+            Action::Macro => "Macro",
+        }
+    }
+
+    // The task a toggle action switches on and off
+    pub fn task(&self) -> Option<TaskType> {
+        match self {
+            Action::Upgrades => Some(TaskType::Upgrades),
+            Action::Souls => Some(TaskType::Souls),
+            Action::Prestige => Some(TaskType::Prestige),
+            Action::Relics => Some(TaskType::Relics),
+            Action::Forge => Some(TaskType::Forge),
+            Action::Alchemy => Some(TaskType::Alchemy),
+            Action::Miner => Some(TaskType::Miner),
+            Action::Macro => Some(TaskType::Macro),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn percent(xp: f64, yp: f64) -> CoordMode {
+        CoordMode::Percent {
+            xp,
+            yp,
+            wp: 0.0,
+            hp: 0.0,
+        }
+    }
+
+    #[test]
+    fn percent_targets_scale_with_the_screen() {
+        let center = percent(0.5, 0.5);
+        assert_eq!(center.resolve(Some((1920, 1080))), Some(Position::new(960, 540)));
+        assert_eq!(center.resolve(Some((2560, 1440))), Some(Position::new(1280, 720)));
+        assert_eq!(center.resolve(Some((1366, 768))), Some(Position::new(683, 384)));
+
+        let corner = percent(1.0, 0.0);
+        assert_eq!(corner.resolve(Some((3840, 2160))), Some(Position::new(3840, 0)));
+    }
+
+    #[test]
+    fn percent_targets_round_to_the_nearest_pixel() {
+        // 0.333 * 1366 = 454.878, 0.667 * 768 = 512.256
+        let target = percent(0.333, 0.667);
+        assert_eq!(target.resolve(Some((1366, 768))), Some(Position::new(455, 512)));
+        // Exactly half a pixel rounds away from zero
+        assert_eq!(percent(0.25, 0.25).resolve(Some((2, 6))), Some(Position::new(1, 2)));
+    }
+
+    #[test]
+    fn percent_targets_need_a_screen_size() {
+        assert_eq!(percent(0.5, 0.5).resolve(None), None);
+        let pixels = CoordMode::from(Position::new(10, 20));
+        assert_eq!(pixels.resolve(None), Some(Position::new(10, 20)));
+    }
+
+    #[test]
+    fn pixel_rectangles_pick_every_pixel_and_none_past_the_edge() {
+        let rect = CoordMode::Pixels {
+            x: 100,
+            y: 200,
+            width: 4,
+            height: 0,
+        };
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut xs = std::collections::BTreeSet::new();
+        for _ in 0..200 {
+            let pos = rect.pick_within(&mut rng).resolve(None).unwrap();
+            assert_eq!(pos.y, 200);
+            xs.insert(pos.x);
+        }
+        assert_eq!(xs.into_iter().collect::<Vec<_>>(), [100, 101, 102, 103]);
+    }
+
+    #[test]
+    fn percent_rectangles_pick_inside_their_bounds() {
+        let rect = CoordMode::Percent {
+            xp: 0.25,
+            yp: 0.5,
+            wp: 0.5,
+            hp: 0.25,
+        };
+        assert!(!rect.overruns_screen());
+        let mut rng = StdRng::seed_from_u64(3);
+        for _ in 0..100 {
+            let pos = rect.pick_within(&mut rng).resolve(Some((1000, 1000))).unwrap();
+            assert!((250..=750).contains(&pos.x) && (500..=750).contains(&pos.y));
+        }
+
+        let overrun = CoordMode::Percent {
+            xp: 0.75,
+            yp: 0.5,
+            wp: 0.5,
+            hp: 0.0,
+        };
+        assert!(overrun.overruns_screen());
+    }
+}
@@ -1,36 +1,145 @@
 use crate::{
-    bot::Bot,
-    config::{APP_NAME, APP_VERSION, Timings},
-    types::TaskType,
+    bot::{Bot, BotSnapshot, TaskSnapshot},
+    config::{Theme, APP_NAME, APP_VERSION},
+    logger::LogLevel,
+    types::Position,
 };
 use anyhow::Result;
 use ratatui::{
     backend::CrosstermBackend,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
-    widgets::{Block, Borders, Gauge, List, ListItem, Paragraph},
+    style::{Modifier, Style},
+    widgets::{Block, Borders, Cell, Gauge, List, ListItem, Paragraph, Row, Sparkline, Table},
     Frame, Terminal,
 };
-use std::io::Stdout;
+use std::{io::Stdout, time::Duration};
 
 pub struct UI {
     pub terminal: Terminal<CrosstermBackend<Stdout>>,
+    log_view: LogView,
+    theme: Theme,
+    // Shows the task table in place of the timer gauges
+    task_table: bool,
+    // Live cursor position, only tracked when the tuning readout is on
+    mouse: Option<Position>,
 }
 
 impl UI {
-    pub fn new(stdout: Stdout) -> Result<Self> {
+    pub fn new(stdout: Stdout, theme: Theme) -> Result<Self> {
         let backend = CrosstermBackend::new(stdout);
         let terminal = Terminal::new(backend)?;
-        Ok(Self { terminal })
+        Ok(Self {
+            terminal,
+            log_view: LogView::default(),
+            theme,
+            task_table: false,
+            mouse: None,
+        })
     }
 
-    pub fn draw(&mut self, bot: &Bot) -> Result<()> {
-        self.terminal.draw(|f| render_ui(f, bot))?;
+    pub fn draw(&mut self, bot: &Bot, quit_prompt: QuitPrompt) -> Result<()> {
+        let log_view = &mut self.log_view;
+        let theme = &self.theme;
+        let mouse = self.mouse;
+        let task_table = self.task_table;
+        self.terminal
+            .draw(|f| render_ui(f, bot, theme, log_view, task_table, mouse, quit_prompt))?;
         Ok(())
     }
+
+    pub fn toggle_task_table(&mut self) {
+        self.task_table = !self.task_table;
+    }
+
+    pub fn set_mouse_position(&mut self, pos: Position) {
+        self.mouse = Some(pos);
+    }
+
+    // Positive scrolls back towards older entries
+    pub fn scroll_logs(&mut self, lines: isize) {
+        self.log_view.offset = self.log_view.offset.saturating_add_signed(lines);
+    }
+
+    pub fn follow_logs(&mut self) {
+        self.log_view.offset = 0;
+    }
+
+    pub fn cycle_log_filter(&mut self) {
+        self.log_view.filter = self.log_view.filter.next();
+        self.log_view.offset = 0;
+    }
+}
+
+// What the footer asks while a quit is under way
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum QuitPrompt {
+    None,
+    // `exit.confirm` is waiting for ESC or Y
+    Confirm,
+    // The first ESC of a double tap was pressed
+    PressAgain,
+}
+
+// Log panel state. An offset of 0 follows the newest entries.
+#[derive(Default)]
+struct LogView {
+    offset: usize,
+    filter: LogFilter,
+}
+
+#[derive(Clone, Copy, Default, PartialEq)]
+enum LogFilter {
+    #[default]
+    All,
+    Only(LogLevel),
+}
+
+impl LogFilter {
+    // Trace entries only show under their own filter, never under All
+    const CYCLE: [LogFilter; 7] = [
+        LogFilter::All,
+        LogFilter::Only(LogLevel::Error),
+        LogFilter::Only(LogLevel::Warning),
+        LogFilter::Only(LogLevel::Success),
+        LogFilter::Only(LogLevel::Task),
+        LogFilter::Only(LogLevel::Info),
+        LogFilter::Only(LogLevel::Trace),
+    ];
+
+    fn next(self) -> Self {
+        let index = Self::CYCLE
+            .iter()
+            .position(|filter| *filter == self)
+            .unwrap_or(0);
+        Self::CYCLE[(index + 1) % Self::CYCLE.len()]
+    }
+
+    fn matches(self, level: LogLevel) -> bool {
+        match self {
+            LogFilter::All => true,
+            LogFilter::Only(only) => only == level,
+        }
+    }
+
+    fn label(self) -> String {
+        match self {
+            LogFilter::All => "All".to_string(),
+            LogFilter::Only(level) => format!("{:?}", level),
+        }
+    }
 }
 
-fn render_ui(f: &mut Frame, bot: &Bot) {
+fn render_ui(
+    f: &mut Frame,
+    bot: &Bot,
+    theme: &Theme,
+    log_view: &mut LogView,
+    task_table: bool,
+    mouse: Option<Position>,
+    quit_prompt: QuitPrompt,
+) {
+    // Read the bot once per frame so every widget shows the same moment
+    let snapshot = &bot.snapshot();
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
@@ -41,37 +150,63 @@ fn render_ui(f: &mut Frame, bot: &Bot) {
         ])
         .split(f.area());
 
-    render_header(f, chunks[0]);
-    render_status(f, chunks[1], bot);
-    render_content(f, chunks[2], bot);
-    render_footer(f, chunks[3]);
+    render_header(f, chunks[0], bot, theme);
+    render_status(f, chunks[1], bot, snapshot, theme);
+    render_content(f, chunks[2], bot, snapshot, theme, log_view, task_table);
+    match mouse {
+        Some(pos) => {
+            let footer = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Min(0), Constraint::Length(36)])
+                .split(chunks[3]);
+            render_footer(f, footer[0], bot, theme, quit_prompt);
+            render_mouse_readout(f, footer[1], bot, theme, pos);
+        }
+        None => render_footer(f, chunks[3], bot, theme, quit_prompt),
+    }
 }
 
-fn render_header(f: &mut Frame, area: Rect) {
-    let header = Paragraph::new(format!("⛏️  {} v{}", APP_NAME, APP_VERSION))
-        .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
+fn render_header(f: &mut Frame, area: Rect, bot: &Bot, theme: &Theme) {
+    let header = Paragraph::new(format!(
+        "⛏️  {} v{} │ Profile: {}{}",
+        APP_NAME,
+        APP_VERSION,
+        bot.profile().name,
+        if bot.is_dry_run() { " │ DRY RUN" } else { "" }
+    ))
+        .style(Style::default().fg(theme.header).add_modifier(Modifier::BOLD))
         .alignment(Alignment::Center)
         .block(Block::default().borders(Borders::ALL));
     f.render_widget(header, area);
 }
 
-fn render_status(f: &mut Frame, area: Rect, bot: &Bot) {
+fn render_status(f: &mut Frame, area: Rect, bot: &Bot, snapshot: &BotSnapshot, theme: &Theme) {
     let chunks = Layout::default()
         .direction(Direction::Horizontal)
         .constraints([
-            Constraint::Percentage(25),
-            Constraint::Percentage(25),
-            Constraint::Percentage(25),
-            Constraint::Percentage(25),
+            Constraint::Percentage(20),
+            Constraint::Percentage(20),
+            Constraint::Percentage(20),
+            Constraint::Percentage(20),
+            Constraint::Percentage(20),
         ])
         .split(area);
 
     let stats = bot.get_stats();
     
     // Status indicator
-    let active = bot.is_active();
-    let status = if active { "● ACTIVE" } else { "● PAUSED" };
-    let color = if active { Color::Green } else { Color::Yellow };
+    let active = snapshot.active;
+    let since = format_duration(bot.time_in_state());
+    let (status, color) = if !snapshot.input_available {
+        ("✖ INPUT UNAVAILABLE".to_string(), theme.log_error)
+    } else if snapshot.mining_active && snapshot.tasks_active {
+        (format!("● ACTIVE for {}", since), theme.active)
+    } else if active {
+        let only = if snapshot.mining_active { "MINING" } else { "TASKS" };
+        (format!("● {} ONLY for {}", only, since), theme.active)
+    } else {
+        (format!("● PAUSED for {}", since), theme.paused)
+    };
     
     let status_widget = Paragraph::new(status)
         .style(Style::default().fg(color).add_modifier(Modifier::BOLD))
@@ -80,90 +215,153 @@ fn render_status(f: &mut Frame, area: Rect, bot: &Bot) {
     f.render_widget(status_widget, chunks[0]);
 
     // Runtime
-    let runtime = format_duration(stats.get_runtime());
-    let runtime_widget = Paragraph::new(format!("Runtime: {}", runtime))
+    let runtime = Duration::from_secs(snapshot.runtime_secs);
+    let runtime_text = match bot.get_config().limits.max_runtime() {
+        Some(max) if active => format!(
+            "Runtime: {} ({} left)",
+            format_duration(runtime),
+            format_duration(max.saturating_sub(runtime))
+        ),
+        _ => format!("Runtime: {}", format_duration(runtime)),
+    };
+    let runtime_widget = Paragraph::new(runtime_text)
         .alignment(Alignment::Center)
         .block(Block::default().borders(Borders::ALL));
     f.render_widget(runtime_widget, chunks[1]);
 
     // Total clicks
-    let clicks = stats.get_clicks();
-    let clicks_widget = Paragraph::new(format!("Clicks: {}", format_number(clicks)))
+    let clicks = snapshot.clicks;
+    let all_time = stats.get_lifetime().clicks;
+    let clicks_text = match bot.get_config().limits.max_clicks() {
+        Some(max) => format!(
+            "Clicks: {} / {} ({} all-time)",
+            format_number(clicks),
+            format_number(max),
+            format_number(all_time)
+        ),
+        None => format!("Clicks: {} ({} all-time)", format_number(clicks), format_number(all_time)),
+    };
+    let clicks_widget = Paragraph::new(clicks_text)
         .alignment(Alignment::Center)
         .block(Block::default().borders(Borders::ALL));
     f.render_widget(clicks_widget, chunks[2]);
 
-    // CPM
-    let cpm = stats.get_cpm();
-    let cpm_widget = Paragraph::new(format!("{} CPM", cpm))
+    // CPM, blank until the warmup has passed
+    let cps = bot.get_config().mining.cps;
+    let cpm_text = if active && stats.in_warmup() {
+        format!("— CPM (warming up) @ {} CPS", cps)
+    } else {
+        format!(
+            "{} CPM (avg {}, peak {}) @ {} CPS",
+            stats.get_windowed_cpm(),
+            snapshot.cpm,
+            stats.get_peak_cpm(),
+            cps
+        )
+    };
+    let cpm_widget = Paragraph::new(cpm_text)
         .alignment(Alignment::Center)
         .block(Block::default().borders(Borders::ALL));
     f.render_widget(cpm_widget, chunks[3]);
+
+    // Next task due
+    let next_text = match bot.next_due() {
+        Some((task_type, left)) if left.is_zero() => format!("Next: {} due now", task_type.name()),
+        Some((task_type, left)) => {
+            format!("Next: {} in {}", task_type.name(), format_duration(left))
+        }
+        None => "Next: no tasks enabled".to_string(),
+    };
+    let next_widget = Paragraph::new(next_text)
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL));
+    f.render_widget(next_widget, chunks[4]);
 }
 
-fn render_content(f: &mut Frame, area: Rect, bot: &Bot) {
+fn render_content(
+    f: &mut Frame,
+    area: Rect,
+    bot: &Bot,
+    snapshot: &BotSnapshot,
+    theme: &Theme,
+    log_view: &mut LogView,
+    task_table: bool,
+) {
     let chunks = Layout::default()
         .direction(Direction::Horizontal)
         .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
         .split(area);
 
-    render_timers(f, chunks[0], bot);
-    render_logs(f, chunks[1], bot);
+    let right = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(6), Constraint::Min(4)])
+        .split(chunks[1]);
+
+    if task_table {
+        render_task_table(f, chunks[0], bot, snapshot, theme);
+    } else {
+        render_timers(f, chunks[0], snapshot, theme);
+    }
+    render_cpm_graph(f, right[0], bot, theme);
+    render_logs(f, right[1], bot, theme, log_view);
+}
+
+fn render_cpm_graph(f: &mut Frame, area: Rect, bot: &Bot, theme: &Theme) {
+    let history = bot.get_stats().get_cpm_history();
+    let max = history.iter().copied().max().unwrap_or(0);
+
+    // Show the newest samples that fit, right-aligned like a scrolling chart
+    let width = area.width.saturating_sub(2) as usize;
+    let visible = &history[history.len().saturating_sub(width)..];
+
+    let sparkline = Sparkline::default()
+        .block(Block::default()
+            .title(format!("📈 CPM (last {}s, max {})", history.len(), max))
+            .borders(Borders::ALL))
+        .data(visible)
+        .style(Style::default().fg(theme.graph));
+    f.render_widget(sparkline, area);
 }
 
-fn render_timers(f: &mut Frame, area: Rect, bot: &Bot) {
+fn render_timers(f: &mut Frame, area: Rect, snapshot: &BotSnapshot, theme: &Theme) {
     let block = Block::default()
         .title("⏱️  Task Timers")
         .borders(Borders::ALL);
     let inner = block.inner(area);
     f.render_widget(block, area);
 
+    // One gauge per task, so a new task type shows up without touching the layout
+    let constraints: Vec<Constraint> = snapshot
+        .tasks
+        .iter()
+        .map(|_| Constraint::Length(3))
+        .chain(std::iter::once(Constraint::Min(0)))
+        .collect();
     let chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(3),
-            Constraint::Length(3),
-            Constraint::Length(3),
-            Constraint::Min(0),
-        ])
+        .constraints(constraints)
         .margin(1)
         .split(inner);
 
-    let task_manager = bot.get_task_manager();
-
-    render_task_timer(f, chunks[0], bot, TaskType::Upgrades, &task_manager);
-    render_task_timer(f, chunks[1], bot, TaskType::Souls, &task_manager);
-    render_task_timer(f, chunks[2], bot, TaskType::Prestige, &task_manager);
+    for (chunk, task) in chunks.iter().zip(&snapshot.tasks) {
+        render_task_timer(f, *chunk, theme, task);
+    }
 }
 
-fn render_task_timer(
-    f: &mut Frame,
-    area: Rect,
-    bot: &Bot,
-    task_type: TaskType,
-    task_manager: &crate::bot::TaskManager,
-) {
-    let enabled = bot.is_task_enabled(task_type);
-    let remaining = task_manager.get_time_until_next(task_type);
+fn render_task_timer(f: &mut Frame, area: Rect, theme: &Theme, task: &TaskSnapshot) {
+    let enabled = task.enabled;
+    let remaining = Duration::from_secs(task.secs_until_next);
     
-    let total_secs = match task_type {
-        TaskType::Upgrades => Timings::UPGRADE_INTERVAL.as_secs(),
-        TaskType::Souls => Timings::SOULS_INTERVAL.as_secs(),
-        TaskType::Prestige => Timings::PRESTIGE_INTERVAL.as_secs(),
-    };
+    let total_secs = task.period_secs.max(1);
     
-    let percent = ((total_secs - remaining.as_secs()) * 100 / total_secs) as u16;
-    let color = match task_type {
-        TaskType::Upgrades => Color::Cyan,
-        TaskType::Souls => Color::Magenta,
-        TaskType::Prestige => Color::Yellow,
-    };
+    let percent = (total_secs.saturating_sub(task.secs_until_next) * 100 / total_secs) as u16;
+    let color = theme.task(task.task);
 
     let gauge = Gauge::default()
         .block(Block::default()
-            .title(format!("{} [{}]", task_type.name(), if enabled { "ON" } else { "OFF" }))
+            .title(format!("{} [{}]", task.name, if enabled { "ON" } else { "OFF" }))
             .borders(Borders::NONE))
-        .gauge_style(Style::default().fg(if enabled { color } else { Color::DarkGray }))
+        .gauge_style(Style::default().fg(if enabled { color } else { theme.disabled }))
         .percent(if enabled { percent } else { 0 })
         .label(if enabled {
             format!("Next in: {}", format_duration(remaining))
@@ -173,41 +371,159 @@ fn render_task_timer(
     f.render_widget(gauge, area);
 }
 
-fn render_logs(f: &mut Frame, area: Rect, bot: &Bot) {
+// Run counts, success rates and timings per task, from the stats counters and the task timers.
+// Success shows "-" until a run has been verified against the screen.
+fn render_task_table(
+    f: &mut Frame,
+    area: Rect,
+    bot: &Bot,
+    snapshot: &BotSnapshot,
+    theme: &Theme,
+) {
+    let stats = bot.get_stats();
+
+    let rows: Vec<Row> = snapshot
+        .tasks
+        .iter()
+        .map(|task| {
+            let (task_type, enabled) = (task.task, task.enabled);
+            let last_run = stats
+                .get_task_last_run(task_type)
+                .map_or_else(|| "never".to_string(), |ago| format!("{} ago", format_duration(ago)));
+            let next = if enabled {
+                format_duration(Duration::from_secs(task.secs_until_next))
+            } else {
+                "off".to_string()
+            };
+            let success = stats
+                .get_task_success_rate(task_type)
+                .map_or_else(|| "-".to_string(), |rate| format!("{}%", rate));
+            Row::new([
+                Cell::from(task.name),
+                Cell::from(stats.get_task_runs(task_type).to_string()),
+                Cell::from(success),
+                Cell::from(last_run),
+                Cell::from(next),
+            ])
+            .style(Style::default().fg(if enabled { theme.task(task_type) } else { theme.disabled }))
+        })
+        .collect();
+
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(10),
+            Constraint::Length(6),
+            Constraint::Length(8),
+            Constraint::Min(10),
+            Constraint::Length(8),
+        ],
+    )
+        .header(
+            Row::new(["Task", "Runs", "Success", "Last run", "Next"])
+                .style(Style::default().add_modifier(Modifier::BOLD))
+                .bottom_margin(1),
+        )
+        .block(Block::default().title("📊 Tasks").borders(Borders::ALL));
+    f.render_widget(table, area);
+}
+
+fn render_logs(f: &mut Frame, area: Rect, bot: &Bot, theme: &Theme, log_view: &mut LogView) {
     let logger = bot.get_logger();
-    let entries = logger.get_entries();
-    
-    let log_items: Vec<ListItem> = entries
+    let entries = match log_view.filter {
+        LogFilter::Only(LogLevel::Trace) => logger.get_traces(),
+        _ => logger.get_entries(),
+    };
+    let height = area.height.saturating_sub(2) as usize;
+
+    let filtered: Vec<_> = entries
+        .iter()
+        .filter(|entry| log_view.filter.matches(entry.level))
+        .collect();
+    log_view.offset = log_view.offset.min(filtered.len().saturating_sub(height));
+
+    let log_items: Vec<ListItem> = filtered
         .iter()
         .rev()
-        .take(area.height as usize - 2)
+        .skip(log_view.offset)
+        .take(height)
         .map(|entry| {
             let timestamp = entry.timestamp.format("%H:%M:%S");
             let text = format!(
                 "[{}] {} {}",
                 timestamp,
                 entry.level.icon(),
-                entry.message
+                entry.text()
             );
-            ListItem::new(text).style(Style::default().fg(entry.level.color()))
+            ListItem::new(text).style(Style::default().fg(theme.log(entry.level)))
         })
         .collect();
 
+    let mut title = format!("📋 Activity Log [{}]", log_view.filter.label());
+    if log_view.offset > 0 {
+        title.push_str(&format!(" ↑{} (End to follow)", log_view.offset));
+    }
     let logs_list = List::new(log_items)
-        .block(Block::default().borders(Borders::ALL).title("📋 Activity Log"));
+        .block(Block::default().borders(Borders::ALL).title(title));
     f.render_widget(logs_list, area);
 }
 
-fn render_footer(f: &mut Frame, area: Rect) {
-    let help = Paragraph::new("[F1] Toggle │ [F2] Upgrades │ [F3] Souls │ [F4] Prestige │ [ESC] Exit")
-        .style(Style::default().fg(Color::DarkGray))
+fn render_footer(f: &mut Frame, area: Rect, bot: &Bot, theme: &Theme, quit_prompt: QuitPrompt) {
+    let (text, color) = if quit_prompt == QuitPrompt::Confirm {
+        ("Quit? [ESC/Y] Confirm │ Any other key to cancel".to_string(), theme.prompt)
+    } else if quit_prompt == QuitPrompt::PressAgain {
+        ("Press ESC again to exit".to_string(), theme.prompt)
+    } else {
+        let profile = bot.profile();
+        let mut hints: Vec<String> = profile
+            .keybindings
+            .bindings()
+            .iter()
+            .map(|(action, key)| format!("[{}] {}", key, action.label()))
+            .collect();
+        hints.push(format!("[{}] Kill", profile.keybindings.kill_switch));
+        if !profile.keybindings.run_now.0.is_empty() {
+            hints.push(format!("[{}+task] Run now", profile.keybindings.run_now));
+        }
+        hints.push("[↑↓/Tab] Log".to_string());
+        hints.push("[T] Tasks".to_string());
+        let config = bot.get_config();
+        let exit_key = if config.exit.double_tap().is_some() && !config.exit.confirm {
+            "[ESC ESC] Exit"
+        } else {
+            "[ESC] Exit"
+        };
+        hints.push(exit_key.to_string());
+        (hints.join(" │ "), theme.footer)
+    };
+
+    let help = Paragraph::new(text)
+        .style(Style::default().fg(color))
         .alignment(Alignment::Center)
         .block(Block::default().borders(Borders::TOP));
     f.render_widget(help, area);
 }
 
+// Shows the cursor position, plus the reference-resolution equivalent when positions are scaled
+fn render_mouse_readout(f: &mut Frame, area: Rect, bot: &Bot, theme: &Theme, pos: Position) {
+    let resolution = &bot.get_config().resolution;
+    let mut text = format!("🖱 ({}, {})", pos.x, pos.y);
+    if let Some(screen) = bot.get_screen_size() {
+        if resolution.scale_factors(screen) != (1.0, 1.0) {
+            let reference = resolution.unscale_position(pos, screen);
+            text.push_str(&format!(" → ref ({}, {})", reference.x, reference.y));
+        }
+    }
+
+    let readout = Paragraph::new(text)
+        .style(Style::default().fg(theme.prompt))
+        .alignment(Alignment::Right)
+        .block(Block::default().borders(Borders::TOP));
+    f.render_widget(readout, area);
+}
+
 // Utility functions
-fn format_duration(d: std::time::Duration) -> String {
+pub fn format_duration(d: std::time::Duration) -> String {
     let secs = d.as_secs();
     if secs < 60 {
         format!("{}s", secs)